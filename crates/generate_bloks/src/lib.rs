@@ -1 +1,3 @@
+pub mod spec;
 
+pub use crate::spec::{Axis, BlokSpec, Face, Feature};
@@ -0,0 +1,353 @@
+//! What a blok looks like, described in TOML:
+//!
+//! ```toml
+//! name = "brick-2x4"
+//! footprint = [2, 4]
+//! height = 3
+//!
+//! [[feature]]
+//! kind = "studs"
+//!
+//! [[feature]]
+//! kind = "tubes"
+//!
+//! [[feature]]
+//! kind = "hole"
+//! axis = "y"
+//! at = [1, 1.5]
+//! diameter = 4.8
+//!
+//! [[feature]]
+//! kind = "text"
+//! text = "2x4"
+//! face = "front"
+//! ```
+//!
+//! The footprint is in studs along X and Y, and the height in plates. Hole
+//! positions are in studs across the blok and plates up it, measured from
+//! its lower corner, and every other length is in mm.
+
+use std::path::Path;
+
+use path_abs::{PathDir, PathFile, PathInfo};
+use project::{Failure, Nothing, Outcome};
+use serde::{Deserialize, Serialize};
+
+/// The distance between stud centres.
+pub const STUD_PITCH: f64 = 8.0;
+pub const PLATE_HEIGHT: f64 = 3.2;
+/// The largest footprint side, in studs, and height, in plates.
+pub const MAX_SIZE: u32 = 48;
+
+const KEYS: [&str; 4] = ["name", "footprint", "height", "feature"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlokSpec {
+    pub name: String,
+    /// Studs along X and Y.
+    pub footprint: [u32; 2],
+    /// In plates, three to a brick.
+    pub height: u32,
+    #[serde(default, rename = "feature")]
+    pub features: Vec<Feature>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Feature {
+    /// One on top of every stud position, except those in `skip`.
+    Studs {
+        #[serde(default)]
+        hollow: bool,
+        /// Stud positions, counted from zero along X and Y.
+        #[serde(default)]
+        skip: Vec<[u32; 2]>,
+    },
+    /// Underneath, between the studs, so bloks below can grip them.
+    // Empty braces rather than a unit variant, so unknown keys are still
+    // rejected.
+    Tubes {},
+    /// Straight through the blok along `axis`.
+    Hole {
+        axis: Axis,
+        /// Across the blok and up it: studs along X and Y for a vertical
+        /// hole, or studs along the face and plates up it otherwise.
+        at: [f64; 2],
+        diameter: f64,
+    },
+    Text {
+        text: String,
+        face: Face,
+        /// The height of a capital letter.
+        #[serde(default = "Feature::default_text_size")]
+        size: f64,
+        /// How far the text stands out of the face, or is cut into it if
+        /// `engrave` is set.
+        #[serde(default = "Feature::default_text_depth")]
+        depth: f64,
+        #[serde(default)]
+        engrave: bool,
+    },
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+/// Front faces -Y, and left faces -X.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Face {
+    Top,
+    Bottom,
+    Front,
+    Back,
+    Left,
+    Right,
+}
+
+impl BlokSpec {
+    pub fn from_toml(source: &str) -> Result<BlokSpec, Failure> {
+        check_keys(source)?;
+
+        let spec: BlokSpec = toml::from_str(source)?;
+        spec.validate()?;
+
+        Ok(spec)
+    }
+
+    /// Reads and validates the spec at `path`, which must exist.
+    pub fn load(path: impl AsRef<Path>) -> Result<BlokSpec, Failure> {
+        let path = path.as_ref();
+        let file = PathFile::new(path)
+            .map_err(|e| format!("Couldn't find blok spec {}: {}", path.display(), e))?;
+        let source = file
+            .read_string()
+            .map_err(|e| format!("Couldn't read blok spec {}: {}", file.display(), e))?;
+
+        BlokSpec::from_toml(&source)
+            .map_err(|e| format!("Invalid blok spec {}: {}", file.display(), e).into())
+    }
+
+    /// Every `.toml` file directly in `dir`, sorted by path.
+    pub fn load_dir(dir: impl AsRef<Path>) -> Result<Vec<(PathFile, BlokSpec)>, Failure> {
+        let dir = dir.as_ref();
+        let dir = PathDir::new(dir)
+            .map_err(|e| format!("Couldn't find blok specs in {}: {}", dir.display(), e))?;
+
+        let mut files = vec![];
+        for entry in dir.list()? {
+            if let Ok(file) = PathFile::try_from(entry?) {
+                if file
+                    .extension()
+                    .is_some_and(|extension| extension == "toml")
+                {
+                    files.push(file);
+                }
+            }
+        }
+        files.sort();
+
+        files
+            .into_iter()
+            .map(|file| BlokSpec::load(&file).map(|spec| (file, spec)))
+            .collect()
+    }
+
+    /// Width, depth and height in mm.
+    pub fn size(&self) -> [f64; 3] {
+        let [x, y] = self.footprint;
+
+        [
+            x as f64 * STUD_PITCH,
+            y as f64 * STUD_PITCH,
+            self.height as f64 * PLATE_HEIGHT,
+        ]
+    }
+
+    fn validate(&self) -> Outcome {
+        if self.name.trim().is_empty() {
+            return Err("The name can't be empty".into());
+        }
+
+        let [x, y] = self.footprint;
+        if !(1..=MAX_SIZE).contains(&x) || !(1..=MAX_SIZE).contains(&y) {
+            return Err(format!(
+                "The footprint must be 1 to {} studs each way, not {}x{}",
+                MAX_SIZE, x, y
+            )
+            .into());
+        }
+
+        if !(1..=MAX_SIZE).contains(&self.height) {
+            return Err(format!(
+                "The height must be 1 to {} plates, not {}",
+                MAX_SIZE, self.height
+            )
+            .into());
+        }
+
+        for (index, feature) in self.features.iter().enumerate() {
+            feature
+                .validate(self)
+                .map_err(|e| format!("[[feature]] #{} ({}): {}", index + 1, feature.kind(), e))?;
+        }
+
+        Ok(Nothing)
+    }
+}
+
+// toml only reports unknown top-level keys at the start of the file, so
+// find them first.
+fn check_keys(source: &str) -> Outcome {
+    let value: toml::Value = source.parse()?;
+    let table = match value.as_table() {
+        Some(table) => table,
+        None => return Ok(Nothing),
+    };
+
+    for key in table.keys() {
+        if KEYS.contains(&key.as_str()) {
+            continue;
+        }
+
+        let line = source.lines().position(|line| {
+            let line = line.trim_start().trim_start_matches('[');
+            line.strip_prefix(key.as_str())
+                .is_some_and(|rest| rest.trim_start().starts_with(['=', ']', '.']))
+        });
+        let location = match line {
+            Some(line) => format!(" on line {}", line + 1),
+            None => String::new(),
+        };
+
+        return Err(format!(
+            "Unknown key `{}`{}, expected one of {}",
+            key,
+            location,
+            KEYS.join(", ")
+        )
+        .into());
+    }
+
+    Ok(Nothing)
+}
+
+impl Feature {
+    fn default_text_size() -> f64 {
+        2.0
+    }
+
+    fn default_text_depth() -> f64 {
+        0.3
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Feature::Studs { .. } => "studs",
+            Feature::Tubes {} => "tubes",
+            Feature::Hole { .. } => "hole",
+            Feature::Text { .. } => "text",
+        }
+    }
+
+    fn validate(&self, spec: &BlokSpec) -> Outcome {
+        let [x, y] = spec.footprint;
+        let positive = |length: f64| length > 0.0 && length.is_finite();
+
+        match self {
+            Feature::Studs { skip, .. } => {
+                for &[sx, sy] in skip {
+                    if sx >= x || sy >= y {
+                        return Err(format!(
+                            "Can't skip stud [{}, {}], outside the {}x{} footprint",
+                            sx, sy, x, y
+                        )
+                        .into());
+                    }
+                }
+            }
+            Feature::Tubes {} => {
+                if x < 2 || y < 2 {
+                    return Err(
+                        format!("Tubes need a footprint of at least 2x2, not {}x{}", x, y).into(),
+                    );
+                }
+            }
+            Feature::Hole { axis, at, diameter } => {
+                if !positive(*diameter) {
+                    return Err(format!("The diameter must be positive, not {}", diameter).into());
+                }
+
+                let [width, depth, height] = spec.size();
+                // The hole's position and the blok's size across it, in mm.
+                let (across, limits) = match axis {
+                    Axis::X => ([at[0] * STUD_PITCH, at[1] * PLATE_HEIGHT], [depth, height]),
+                    Axis::Y => ([at[0] * STUD_PITCH, at[1] * PLATE_HEIGHT], [width, height]),
+                    Axis::Z => ([at[0] * STUD_PITCH, at[1] * STUD_PITCH], [width, depth]),
+                };
+
+                let radius = diameter / 2.0;
+                let inside = across.iter().zip(&limits).all(|(position, limit)| {
+                    *position - radius > 0.0 && *position + radius < *limit
+                });
+                if !inside {
+                    return Err(format!(
+                        "A {}mm hole at [{}, {}] along {:?} doesn't fit inside the blok",
+                        diameter, at[0], at[1], axis
+                    )
+                    .into());
+                }
+            }
+            Feature::Text {
+                text,
+                face,
+                size,
+                depth,
+                engrave,
+            } => {
+                if text.trim().is_empty() {
+                    return Err("The text can't be empty".into());
+                }
+
+                if !positive(*size) {
+                    return Err(format!("The size must be positive, not {}", size).into());
+                }
+
+                if !positive(*depth) {
+                    return Err(format!("The depth must be positive, not {}", depth).into());
+                }
+
+                let [width, blok_depth, height] = spec.size();
+                let (face_height, thickness) = match face {
+                    Face::Top | Face::Bottom => (width.min(blok_depth), height),
+                    Face::Front | Face::Back => (height, blok_depth),
+                    Face::Left | Face::Right => (height, width),
+                };
+
+                if *size > face_height {
+                    return Err(format!(
+                        "{}mm text is taller than the {:?} face, which is {}mm",
+                        size, face, face_height
+                    )
+                    .into());
+                }
+
+                if *engrave && *depth >= thickness {
+                    return Err(format!(
+                        "Engraving {}mm deep would cut through the blok, which is {}mm thick there",
+                        depth, thickness
+                    )
+                    .into());
+                }
+            }
+        }
+
+        Ok(Nothing)
+    }
+}
@@ -0,0 +1,114 @@
+use generate_bloks::{Axis, BlokSpec, Feature};
+
+const BRICK: &str = r#"
+name = "brick-2x4"
+footprint = [2, 4]
+height = 3
+"#;
+
+fn error(source: &str) -> String {
+    BlokSpec::from_toml(source).unwrap_err().to_string()
+}
+
+fn with_feature(feature: &str) -> String {
+    format!("{}\n[[feature]]\n{}", BRICK, feature)
+}
+
+#[test]
+fn valid() {
+    let spec = BlokSpec::from_toml(&with_feature(
+        "kind = \"hole\"\naxis = \"y\"\nat = [1, 1.5]\ndiameter = 4.8\n\n[[feature]]\nkind = \"tubes\"",
+    ))
+    .unwrap();
+
+    assert_eq!(spec.footprint, [2, 4]);
+    assert_eq!(spec.size(), [16.0, 32.0, 3.0 * 3.2]);
+    assert_eq!(
+        spec.features,
+        vec![
+            Feature::Hole {
+                axis: Axis::Y,
+                at: [1.0, 1.5],
+                diameter: 4.8,
+            },
+            Feature::Tubes {},
+        ]
+    );
+}
+
+#[test]
+fn unknown_key() {
+    let message = error(&format!("{}colour = \"red\"\n", BRICK));
+
+    assert!(message.contains("`colour`"), "{}", message);
+    assert!(message.contains("line 5"), "{}", message);
+}
+
+#[test]
+fn unknown_feature_key() {
+    let message = error(&with_feature(
+        "kind = \"hole\"\naxis = \"z\"\nat = [1, 1]\ndiameter = 4.8\nsize = 3",
+    ));
+    assert!(message.contains("unknown field `size`"), "{}", message);
+
+    let message = error(&with_feature("kind = \"tubes\"\nbogus = 1"));
+    assert!(message.contains("unknown field `bogus`"), "{}", message);
+}
+
+#[test]
+fn unknown_feature_kind() {
+    let message = error(&with_feature("kind = \"knob\""));
+
+    assert!(message.contains("unknown variant `knob`"), "{}", message);
+}
+
+#[test]
+fn footprint_out_of_range() {
+    let message = error("name = \"a\"\nfootprint = [0, 4]\nheight = 3");
+    assert!(message.contains("not 0x4"), "{}", message);
+
+    let message = error("name = \"a\"\nfootprint = [2, 49]\nheight = 3");
+    assert!(message.contains("not 2x49"), "{}", message);
+}
+
+#[test]
+fn height_out_of_range() {
+    let message = error("name = \"a\"\nfootprint = [2, 4]\nheight = 0");
+
+    assert!(
+        message.contains("height must be 1 to 48 plates, not 0"),
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn hole_outside() {
+    let message = error(&with_feature(
+        "kind = \"hole\"\naxis = \"z\"\nat = [1, 9]\ndiameter = 4.8",
+    ));
+
+    assert!(message.starts_with("[[feature]] #1 (hole)"), "{}", message);
+    assert!(message.contains("doesn't fit"), "{}", message);
+}
+
+#[test]
+fn engraved_too_deep() {
+    let message = error(&with_feature(
+        "kind = \"text\"\ntext = \"2x4\"\nface = \"front\"\ndepth = 40\nengrave = true",
+    ));
+
+    assert!(message.starts_with("[[feature]] #1 (text)"), "{}", message);
+    assert!(message.contains("cut through"), "{}", message);
+}
+
+#[test]
+fn missing_file() {
+    let message = BlokSpec::load("no/such/blok.toml").unwrap_err().to_string();
+
+    assert!(
+        message.starts_with("Couldn't find blok spec no/such/blok.toml"),
+        "{}",
+        message
+    );
+}
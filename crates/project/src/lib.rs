@@ -141,12 +141,12 @@ where
     fn file(&self, root: &PathDir, name: Key) -> ExistingRegularFile {
         let files = &self.regular_file_map;
 
-        ExistingRegularFile::from(files.get(&root, name))
+        ExistingRegularFile::from(files.get(root, name))
     }
 
     fn dir(&self, root: &PathDir, name: Key) -> ExistingDirectory {
         let dirs = &self.directory_map;
 
-        ExistingDirectory::from(dirs.get(&root, name))
+        ExistingDirectory::from(dirs.get(root, name))
     }
 }
@@ -5,7 +5,6 @@ use std::{
 
 use amplify_derive::Display;
 use path_abs::{PathAbs, PathDir, PathFile, PathInfo, PathOps};
-use serde::{Deserialize, Serialize};
 
 use crate::utils::{
     error::ProjectError,
@@ -13,22 +12,17 @@ use crate::utils::{
     file::FileType,
 };
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Default)]
 pub enum FileExpectation {
     /// Expect this path to exist. If it doesn't exist, it's an error.
     MustAlreadyExist,
     /// Expect this path not to exist. If it exists, it's an error.
     MayNotAlreadyExist,
     /// This path may or may not exist. If it doesn't exist, make it.
+    #[default]
     Touch,
 }
 
-impl Default for FileExpectation {
-    fn default() -> Self {
-        FileExpectation::Touch
-    }
-}
-
 pub type Touch<T> = fn(&PathAbs) -> Result<T, Failure>;
 
 #[derive(Debug, Clone, Display)]
@@ -150,7 +144,7 @@ impl ProjectPath for AbsoluteRegularFile {
 
     fn default_touch() -> Touch<PathFile> {
         pub fn default_touch(path: &PathAbs) -> Fallible<PathFile> {
-            std::fs::File::create(&path)?;
+            std::fs::File::create(path)?;
 
             Ok(PathFile::new(path)?)
         }
@@ -305,7 +299,7 @@ where
 {
     pub fn new<Q: ProjectPath>(path: Q) -> AbsolutePath<Q> {
         AbsolutePath {
-            path: path.into(),
+            path,
             touch_fn: Q::default_touch(),
             expectation: FileExpectation::Touch,
         }
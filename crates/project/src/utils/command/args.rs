@@ -27,6 +27,7 @@ impl ApplyToCommand for Args {
 }
 
 impl Args {
+    #[allow(clippy::should_implement_trait)]
     pub fn add(mut self, arg: String) -> Args {
         self.inner.push(arg);
         self
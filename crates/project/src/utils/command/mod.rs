@@ -145,6 +145,7 @@ impl CommandExecution {
 pub trait CommandOutcomeOps {
     fn wrap(output: Output) -> Self;
     fn inner(&self) -> &Output;
+    #[allow(clippy::result_large_err)]
     fn into_result(self, command: CommandExecution) -> Result<CommandSuccess, ProjectError>;
 
     fn status(&self) -> ExitStatus {
@@ -7,6 +7,7 @@ use crate::utils::file::FileType;
 use super::command::CommandExecution;
 
 #[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
 pub enum ProjectError {
     MissingFile {
         path: PathAbs,
@@ -0,0 +1,150 @@
+use std::time::{Duration, Instant};
+
+use rpt::image::RgbImage;
+use serde::{Deserialize, Serialize};
+
+// A pixel counts as converged once its standard error is below the target.
+// Rendering stops when this share of pixels have converged, so that a few
+// fireflies don't keep it going until the budget runs out.
+const CONVERGED_FRACTION: f64 = 0.995;
+const SRGB_GAMMA: f64 = 2.2;
+
+/// Keep rendering batches of samples until the image stops changing. rpt
+/// samples the whole image at once, so every pixel gets the same number of
+/// samples; it's when to stop that's decided per pixel.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdaptiveSampling {
+    /// Samples per pixel in each batch.
+    pub batch: u32,
+    /// The standard error, in linear intensity from 0 to 1, that a pixel's
+    /// mean has to fall below.
+    pub target: f64,
+    pub max_samples: u32,
+    /// Stop after this long even if the image hasn't converged.
+    pub max_seconds: Option<f64>,
+}
+
+impl AdaptiveSampling {
+    pub const DEFAULT: AdaptiveSampling = AdaptiveSampling {
+        batch: 4,
+        target: 0.005,
+        max_samples: 256,
+        max_seconds: None,
+    };
+
+    pub fn target(self, target: f64) -> AdaptiveSampling {
+        AdaptiveSampling { target, ..self }
+    }
+
+    pub fn max_samples(self, max_samples: u32) -> AdaptiveSampling {
+        AdaptiveSampling {
+            max_samples,
+            ..self
+        }
+    }
+
+    pub fn max_seconds(self, max_seconds: f64) -> AdaptiveSampling {
+        AdaptiveSampling {
+            max_seconds: Some(max_seconds),
+            ..self
+        }
+    }
+}
+
+impl Default for AdaptiveSampling {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Running per-pixel statistics over batches, in linear intensity.
+struct Estimate {
+    batches: u32,
+    mean: Vec<f64>,
+    // The sum of squared differences from the mean, as in Welford's method.
+    squares: Vec<f64>,
+}
+
+impl Estimate {
+    fn add(&mut self, image: &RgbImage) {
+        self.batches += 1;
+        let batches = self.batches as f64;
+
+        for (index, channel) in image.as_raw().iter().enumerate() {
+            let value = (*channel as f64 / 255.0).powf(SRGB_GAMMA);
+            let delta = value - self.mean[index];
+
+            self.mean[index] += delta / batches;
+            self.squares[index] += delta * (value - self.mean[index]);
+        }
+    }
+
+    fn converged(&self, target: f64) -> bool {
+        if self.batches < 3 {
+            return false;
+        }
+
+        let batches = self.batches as f64;
+        let pixels = self.mean.len() / 3;
+        let converged = self
+            .squares
+            .chunks(3)
+            .filter(|channels| {
+                channels.iter().all(|squares| {
+                    let variance = squares / (batches - 1.0);
+                    (variance / batches).sqrt() < target
+                })
+            })
+            .count();
+
+        converged as f64 >= pixels as f64 * CONVERGED_FRACTION
+    }
+
+    fn image(&self, width: u32, height: u32) -> RgbImage {
+        let bytes = self
+            .mean
+            .iter()
+            .map(|value| (value.clamp(0.0, 1.0).powf(1.0 / SRGB_GAMMA) * 255.0).round() as u8)
+            .collect();
+
+        RgbImage::from_raw(width, height, bytes).expect("Estimate has the wrong size")
+    }
+}
+
+/// Renders batches with `render`, which is given the number of samples to
+/// take, until the image converges or the budget runs out. Returns the
+/// image and how many samples per pixel it took. At least `min_samples` are
+/// always taken.
+pub(crate) fn render(
+    settings: &AdaptiveSampling,
+    min_samples: u32,
+    (width, height): (u32, u32),
+    render: impl Fn(u32) -> RgbImage,
+) -> (RgbImage, u32) {
+    let start = Instant::now();
+    let budget = settings.max_seconds.map(Duration::from_secs_f64);
+    let batch = settings.batch.max(1);
+    let size = (width * height * 3) as usize;
+
+    let mut estimate = Estimate {
+        batches: 0,
+        mean: vec![0.0; size],
+        squares: vec![0.0; size],
+    };
+    let mut samples = 0;
+
+    loop {
+        estimate.add(&render(batch));
+        samples += batch;
+
+        let out_of_budget = samples + batch > settings.max_samples.max(batch)
+            || budget.is_some_and(|budget| start.elapsed() >= budget);
+
+        if samples >= min_samples && (out_of_budget || estimate.converged(settings.target)) {
+            break;
+        }
+    }
+
+    (estimate.image(width, height), samples)
+}
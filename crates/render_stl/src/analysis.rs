@@ -0,0 +1,91 @@
+use std::collections::HashSet;
+
+use rpt::{glm::DVec3, Triangle};
+
+use crate::{
+    geometry::{area_vector, bounds},
+    location::Position,
+};
+
+/// Measurements of a mesh, in the same units as the STL (usually mm).
+#[derive(Debug, Copy, Clone)]
+pub struct MeshAnalysis {
+    pub min: Position,
+    pub max: Position,
+    pub dimensions: Position,
+    /// Signed volume. Negative if the triangles are wound inside-out.
+    pub volume: f64,
+    pub surface_area: f64,
+    pub triangle_count: usize,
+    /// Distinct vertex positions, so vertices shared between triangles are
+    /// only counted once.
+    pub vertex_count: usize,
+    /// The centroid of the enclosed solid, or of the surface if the mesh
+    /// doesn't enclose any volume.
+    pub center_of_mass: Position,
+}
+
+impl MeshAnalysis {
+    pub fn of(triangles: &[Triangle]) -> MeshAnalysis {
+        let mut volume = 0.0;
+        let mut surface_area = 0.0;
+        let mut volume_moment = DVec3::zeros();
+        let mut area_moment = DVec3::zeros();
+        let mut vertices = HashSet::new();
+
+        for triangle in triangles {
+            let Triangle { v1, v2, v3, .. } = triangle;
+
+            // Signed volume of the tetrahedron between the triangle and the
+            // origin. Summed over a closed mesh, everything outside cancels.
+            let tetrahedron = v1.dot(&v2.cross(v3)) / 6.0;
+            volume += tetrahedron;
+            volume_moment += (v1 + v2 + v3) * (tetrahedron / 4.0);
+
+            let area = area_vector(triangle).norm();
+            surface_area += area;
+            area_moment += (v1 + v2 + v3) * (area / 3.0);
+
+            for vertex in [v1, v2, v3] {
+                vertices.insert(vertex_key(vertex));
+            }
+        }
+
+        let center_of_mass = if volume.abs() > f64::EPSILON {
+            volume_moment / volume
+        } else if surface_area > 0.0 {
+            area_moment / surface_area
+        } else {
+            DVec3::zeros()
+        };
+
+        let (min, max) = if triangles.is_empty() {
+            (DVec3::zeros(), DVec3::zeros())
+        } else {
+            let bounds = bounds(triangles);
+            (bounds.p_min, bounds.p_max)
+        };
+
+        MeshAnalysis {
+            min: to_position(&min),
+            max: to_position(&max),
+            dimensions: to_position(&(max - min)),
+            volume,
+            surface_area,
+            triangle_count: triangles.len(),
+            vertex_count: vertices.len(),
+            center_of_mass: to_position(&center_of_mass),
+        }
+    }
+}
+
+pub(crate) fn vertex_key(vertex: &DVec3) -> (u64, u64, u64) {
+    // Normalize -0.0 so that it's the same vertex as 0.0.
+    let bits = |n: f64| (n + 0.0).to_bits();
+
+    (bits(vertex.x), bits(vertex.y), bits(vertex.z))
+}
+
+pub(crate) fn to_position(vector: &DVec3) -> Position {
+    (vector.x, vector.y, vector.z)
+}
@@ -1,7 +1,11 @@
 use amplify_derive::{From, Wrapper};
-use std::f64::consts;
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::{f64::consts, fmt};
 
-#[derive(Copy, Clone, Default, From, Wrapper)]
+#[derive(Debug, Copy, Clone, Default, From, Wrapper)]
 pub struct Angle {
     radians: f64,
 }
@@ -10,7 +14,7 @@ impl Angle {
     pub const ZERO: Angle = Angle { radians: 0f64 };
 
     #[inline]
-    pub fn radians(radians: f64) -> Angle {
+    pub const fn radians(radians: f64) -> Angle {
         Angle { radians }
     }
 
@@ -20,4 +24,66 @@ impl Angle {
             radians: degrees * (consts::PI / 180.0f64),
         }
     }
+
+    #[inline]
+    pub fn to_degrees(self) -> f64 {
+        self.radians * (180.0f64 / consts::PI)
+    }
+}
+
+// Angles are written as `"45deg"`, rounded to a billionth of a degree so the
+// round trip through radians doesn't show. `"0.78rad"` and bare numbers (in
+// degrees) are also accepted.
+impl Serialize for Angle {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let degrees = (self.to_degrees() * 1e9).round() / 1e9;
+
+        serializer.collect_str(&format_args!("{}deg", degrees))
+    }
+}
+
+impl<'de> Deserialize<'de> for Angle {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct AngleVisitor;
+
+        impl<'de> Visitor<'de> for AngleVisitor {
+            type Value = Angle;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "an angle like \"45deg\" or \"0.78rad\"")
+            }
+
+            fn visit_f64<E: de::Error>(self, degrees: f64) -> Result<Angle, E> {
+                Ok(Angle::degrees(degrees))
+            }
+
+            fn visit_i64<E: de::Error>(self, degrees: i64) -> Result<Angle, E> {
+                Ok(Angle::degrees(degrees as f64))
+            }
+
+            fn visit_u64<E: de::Error>(self, degrees: u64) -> Result<Angle, E> {
+                Ok(Angle::degrees(degrees as f64))
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Angle, E> {
+                let trimmed = value.trim();
+                let parse = |number: &str| {
+                    number
+                        .trim()
+                        .parse::<f64>()
+                        .map_err(|_| E::invalid_value(de::Unexpected::Str(value), &self))
+                };
+
+                if let Some(degrees) = trimmed.strip_suffix("deg") {
+                    Ok(Angle::degrees(parse(degrees)?))
+                } else if let Some(radians) = trimmed.strip_suffix("rad") {
+                    Ok(Angle::radians(parse(radians)?))
+                } else {
+                    Err(E::invalid_value(de::Unexpected::Str(value), &self))
+                }
+            }
+        }
+
+        deserializer.deserialize_any(AngleVisitor)
+    }
 }
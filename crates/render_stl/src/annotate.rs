@@ -0,0 +1,361 @@
+use rpt::{
+    glm::DVec3,
+    image::{Rgba, RgbaImage},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    analysis::MeshAnalysis,
+    camera::Camera,
+    color::Color,
+    font,
+    location::{position_to_rpt, Location},
+};
+
+// How far bounding box dimensions sit from the box, as a fraction of its
+// longest side.
+const GAP: f64 = 0.08;
+// How far extension lines run past the dimension line, as a fraction of the
+// offset.
+const OVERSHOOT: f64 = 0.15;
+// Between an arrowhead's sides and its line, in radians.
+const ARROW_ANGLE: f64 = 0.45;
+// How dark label backgrounds are.
+const LABEL_SHADE: f64 = 0.6;
+
+/// A measurement between two points. The line is drawn `offset` away from
+/// them, with extension lines back to the points, and labelled with its
+/// length unless it has a `label`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Dimension {
+    pub from: Location,
+    pub to: Location,
+    #[serde(default)]
+    pub offset: Location,
+    pub label: Option<String>,
+}
+
+impl Dimension {
+    pub fn new(from: impl Into<Location>, to: impl Into<Location>) -> Dimension {
+        Dimension {
+            from: from.into(),
+            to: to.into(),
+            offset: Location::ORIGIN,
+            label: None,
+        }
+    }
+
+    pub fn offset(self, offset: impl Into<Location>) -> Dimension {
+        Dimension {
+            offset: offset.into(),
+            ..self
+        }
+    }
+
+    pub fn label(self, label: impl Into<String>) -> Dimension {
+        Dimension {
+            label: Some(label.into()),
+            ..self
+        }
+    }
+
+    pub fn length(&self) -> f64 {
+        (self.to.to_offset() - self.from.to_offset()).norm()
+    }
+}
+
+/// Dimension lines drawn over the rendered image, before any
+/// post-processing. Points are projected through the model's camera, so
+/// they line up with the render from any view.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Annotations {
+    /// Also measure the width, depth and height of everything in the model,
+    /// along whichever edges of its bounding box the camera sees best.
+    pub bounding_box: bool,
+    pub dimensions: Vec<Dimension>,
+    pub color: Color,
+    /// Digits after the decimal point in lengths.
+    pub precision: usize,
+    pub units: String,
+    /// Image pixels per font pixel. Defaults to one per 300 pixels of the
+    /// image's shorter side.
+    pub text_scale: Option<u32>,
+}
+
+impl Annotations {
+    pub fn new() -> Annotations {
+        Annotations {
+            bounding_box: false,
+            dimensions: vec![],
+            color: Color::hex(0xffd040),
+            precision: 1,
+            units: "mm".to_string(),
+            text_scale: None,
+        }
+    }
+
+    pub fn bounding_box(self) -> Annotations {
+        Annotations {
+            bounding_box: true,
+            ..self
+        }
+    }
+
+    pub fn add_dimension(mut self, dimension: Dimension) -> Annotations {
+        self.dimensions.push(dimension);
+        self
+    }
+
+    pub fn color(self, color: impl Into<Color>) -> Annotations {
+        Annotations {
+            color: color.into(),
+            ..self
+        }
+    }
+
+    pub fn precision(self, precision: usize) -> Annotations {
+        Annotations { precision, ..self }
+    }
+
+    pub fn units(self, units: impl Into<String>) -> Annotations {
+        Annotations {
+            units: units.into(),
+            ..self
+        }
+    }
+
+    pub fn text_scale(self, scale: u32) -> Annotations {
+        Annotations {
+            text_scale: Some(scale),
+            ..self
+        }
+    }
+
+    /// `analysis` covers everything in the model, for `bounding_box`.
+    pub(crate) fn draw(
+        &self,
+        image: &mut RgbaImage,
+        camera: &Camera,
+        analysis: Option<&MeshAnalysis>,
+    ) {
+        let mut dimensions = self.dimensions.clone();
+        if let (true, Some(analysis)) = (self.bounding_box, analysis) {
+            dimensions.extend(bounding_box(analysis, camera));
+        }
+
+        let scale = self
+            .text_scale
+            .unwrap_or_else(|| image.width().min(image.height()) / 300)
+            .max(1);
+
+        for dimension in &dimensions {
+            self.draw_dimension(image, camera, dimension, scale);
+        }
+    }
+
+    fn draw_dimension(
+        &self,
+        image: &mut RgbaImage,
+        camera: &Camera,
+        dimension: &Dimension,
+        scale: u32,
+    ) {
+        let (width, height) = image.dimensions();
+        let project = |point: DVec3| {
+            camera
+                .project((point.x, point.y, point.z), width, height)
+                .map(|(x, y)| DVec3::new(x, y, 0.0))
+        };
+
+        let (from, to) = (dimension.from.to_offset(), dimension.to.to_offset());
+        let offset = dimension.offset.to_offset();
+        let (start, end) = match (project(from + offset), project(to + offset)) {
+            (Some(start), Some(end)) => (start, end),
+            _ => return,
+        };
+
+        let (r, g, b) = self.color.rgb();
+        let color = Rgba([r, g, b, 255]);
+        let thickness = scale as f64;
+
+        if offset.norm() > 0.0 {
+            for point in [from, to] {
+                let beyond = point + offset * (1.0 + OVERSHOOT);
+                if let (Some(a), Some(b)) = (project(point), project(beyond)) {
+                    draw_line(image, a, b, thickness, color);
+                }
+            }
+        }
+
+        draw_line(image, start, end, thickness, color);
+
+        let along = end - start;
+        if along.norm() >= 1.0 {
+            let along = along.normalize();
+            let length = 4.0 + 3.0 * thickness;
+
+            for (tip, direction) in [(start, along), (end, -along)] {
+                for side in [-ARROW_ANGLE, ARROW_ANGLE] {
+                    let (sin, cos) = side.sin_cos();
+                    let barb = DVec3::new(
+                        direction.x * cos - direction.y * sin,
+                        direction.x * sin + direction.y * cos,
+                        0.0,
+                    );
+
+                    draw_line(image, tip, tip + barb * length, thickness, color);
+                }
+            }
+        }
+
+        let label = match &dimension.label {
+            Some(label) => label.clone(),
+            None => format!("{:.*} {}", self.precision, dimension.length(), self.units)
+                .trim_end()
+                .to_string(),
+        };
+        draw_label(image, &label, (start + end) / 2.0, scale, color);
+    }
+}
+
+impl Default for Annotations {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// One dimension per axis, along the edge of the box furthest out to the
+// side as the camera sees it, pushed further out from there. Width and
+// depth are measured along the bottom of the box, where it meets the bed.
+// Axes pointing at the camera are left out.
+fn bounding_box(analysis: &MeshAnalysis, camera: &Camera) -> Vec<Dimension> {
+    let (min, max) = (position_to_rpt(analysis.min), position_to_rpt(analysis.max));
+    let center = (min + max) / 2.0;
+    let gap = (max - min).max() * GAP;
+    let view: rpt::Camera = (*camera).into();
+    let view = view.direction;
+
+    let mut dimensions = vec![];
+    for axis in 0..3 {
+        let mut edge = DVec3::zeros();
+        edge[axis] = 1.0;
+
+        let across = view - edge * view.dot(&edge);
+        if across.norm() < 0.1 {
+            continue;
+        }
+        let across = across.normalize();
+
+        let (first, second) = ((axis + 1) % 3, (axis + 2) % 3);
+        let mut best: Option<(DVec3, DVec3)> = None;
+
+        for corner in 0..4 {
+            let mut start = min;
+            for (bit, other) in [(1, first), (2, second)] {
+                if corner & bit != 0 {
+                    start[other] = max[other];
+                }
+            }
+
+            if axis != 2 && start.z != min.z {
+                continue;
+            }
+
+            let outward = start - center;
+            let outward = outward - edge * outward.dot(&edge);
+            let outward = outward - across * outward.dot(&across);
+
+            if best.is_none_or(|(_, best)| outward.norm() > best.norm() + 1e-9) {
+                best = Some((start, outward));
+            }
+        }
+
+        if let Some((start, outward)) = best {
+            if outward.norm() <= 0.0 {
+                continue;
+            }
+
+            let mut end = start;
+            end[axis] = max[axis];
+            let offset = outward.normalize() * gap;
+
+            dimensions.push(
+                Dimension::new(
+                    Location::new(start.x, start.y, start.z),
+                    Location::new(end.x, end.y, end.z),
+                )
+                .offset(Location::new(offset.x, offset.y, offset.z)),
+            );
+        }
+    }
+
+    dimensions
+}
+
+// An antialiased line `thickness` pixels wide, between points in pixel
+// coordinates.
+fn draw_line(image: &mut RgbaImage, a: DVec3, b: DVec3, thickness: f64, color: Rgba<u8>) {
+    let reach = thickness / 2.0 + 1.0;
+    let (width, height) = (image.width() as f64, image.height() as f64);
+
+    let left = (a.x.min(b.x) - reach).floor().clamp(0.0, width) as u32;
+    let right = (a.x.max(b.x) + reach).ceil().clamp(0.0, width) as u32;
+    let top = (a.y.min(b.y) - reach).floor().clamp(0.0, height) as u32;
+    let bottom = (a.y.max(b.y) + reach).ceil().clamp(0.0, height) as u32;
+
+    let segment = b - a;
+    let length = segment.norm_squared();
+
+    for y in top..bottom {
+        for x in left..right {
+            let pixel = DVec3::new(x as f64 + 0.5, y as f64 + 0.5, 0.0);
+            let t = if length > 0.0 {
+                ((pixel - a).dot(&segment) / length).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let distance = (pixel - (a + segment * t)).norm();
+            let coverage = (thickness / 2.0 + 0.5 - distance).clamp(0.0, 1.0);
+
+            if coverage > 0.0 {
+                blend(image, x, y, color, coverage);
+            }
+        }
+    }
+}
+
+// Centred on `center`, on a darkened box so it reads over the render.
+fn draw_label(image: &mut RgbaImage, label: &str, center: DVec3, scale: u32, color: Rgba<u8>) {
+    let (text_width, text_height) = font::text_size(label, scale);
+    let padding = 2 * scale as i64;
+    let left = (center.x - text_width as f64 / 2.0).round() as i64;
+    let top = (center.y - text_height as f64 / 2.0).round() as i64;
+
+    for y in (top - padding)..(top + text_height as i64 + padding) {
+        for x in (left - padding)..(left + text_width as i64 + padding) {
+            if (0..image.width() as i64).contains(&x) && (0..image.height() as i64).contains(&y) {
+                blend(image, x as u32, y as u32, Rgba([0, 0, 0, 255]), LABEL_SHADE);
+            }
+        }
+    }
+
+    font::draw_text(image, label, (left, top), scale, color);
+}
+
+// Over whatever's there, which may be transparent.
+fn blend(image: &mut RgbaImage, x: u32, y: u32, color: Rgba<u8>, alpha: f64) {
+    let pixel = image.get_pixel_mut(x, y);
+    let under = pixel.0[3] as f64 / 255.0 * (1.0 - alpha);
+    let coverage = alpha + under;
+
+    if coverage <= 0.0 {
+        return;
+    }
+
+    for (channel, over) in pixel.0[..3].iter_mut().zip(&color.0) {
+        *channel = ((*channel as f64 * under + *over as f64 * alpha) / coverage).round() as u8;
+    }
+    pixel.0[3] = (coverage * 255.0).round() as u8;
+}
@@ -0,0 +1,325 @@
+use std::{
+    fmt, fs,
+    panic::{self, AssertUnwindSafe},
+    path::{Component, Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex, MutexGuard, PoisonError,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use path_abs::PathFile;
+use project::Failure;
+
+use crate::{
+    cache::RenderCache,
+    mesh::{Mesh, MeshSource},
+    preset::Preset,
+};
+
+/// Renders every mesh under a directory, or matching a glob, into a
+/// directory tree that mirrors the inputs.
+pub struct Batch {
+    jobs: Vec<BatchJob>,
+    preset: Preset,
+    workers: usize,
+    cache: Option<PathBuf>,
+    force: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct BatchJob {
+    pub input: PathBuf,
+    pub output: PathBuf,
+}
+
+#[derive(Debug, Clone)]
+pub struct JobResult {
+    pub job: BatchJob,
+    pub elapsed: Duration,
+    /// The cached image was already up to date.
+    pub skipped: bool,
+    /// Why the job failed, if it did.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BatchSummary {
+    /// In the same order as the batch's jobs.
+    pub results: Vec<JobResult>,
+    pub elapsed: Duration,
+}
+
+impl Batch {
+    /// Every `.stl` file under `input`, at any depth.
+    pub fn directory(input: impl AsRef<Path>, output: impl AsRef<Path>) -> Result<Batch, Failure> {
+        let input = input.as_ref();
+
+        if !input.is_dir() {
+            return Err(format!("{} is not a directory", input.display()).into());
+        }
+
+        let pattern = input.join("**").join("*.[sS][tT][lL]");
+        let pattern = pattern.to_str().ok_or("Input path isn't valid UTF-8")?;
+
+        Batch::matching(pattern, input, output.as_ref())
+    }
+
+    /// Every file matching `pattern`, like `models/**/*.stl`. Outputs are
+    /// placed relative to the part of the pattern before the first wildcard.
+    pub fn glob(pattern: &str, output: impl AsRef<Path>) -> Result<Batch, Failure> {
+        let base: PathBuf = Path::new(pattern)
+            .components()
+            .take_while(|component| match component {
+                Component::Normal(name) => !name.to_string_lossy().contains(&['*', '?', '['][..]),
+                _ => true,
+            })
+            .collect();
+
+        Batch::matching(pattern, &base, output.as_ref())
+    }
+
+    fn matching(pattern: &str, base: &Path, output: &Path) -> Result<Batch, Failure> {
+        let mut jobs = vec![];
+
+        for input in glob::glob(pattern)? {
+            let input = input?;
+
+            if !input.is_file() {
+                continue;
+            }
+
+            // A pattern without wildcards matches only itself.
+            let relative = match input.strip_prefix(base) {
+                Ok(relative) if relative != Path::new("") => relative,
+                _ => Path::new(input.file_name().unwrap_or_default()),
+            };
+            let output = output.join(relative).with_extension("png");
+
+            jobs.push(BatchJob { input, output });
+        }
+
+        jobs.sort_by(|a, b| a.input.cmp(&b.input));
+
+        Ok(Batch {
+            jobs,
+            preset: Preset::DEFAULT,
+            workers: thread::available_parallelism().map_or(1, |count| count.get()),
+            cache: None,
+            force: false,
+        })
+    }
+
+    pub fn preset(self, preset: Preset) -> Batch {
+        Batch { preset, ..self }
+    }
+
+    /// How many meshes to render at once. Defaults to the number of CPUs.
+    pub fn workers(self, workers: usize) -> Batch {
+        Batch {
+            workers: workers.max(1),
+            ..self
+        }
+    }
+
+    /// Skips jobs whose output is unchanged since it was rendered with the
+    /// same fingerprint, as recorded in the manifest at `manifest`.
+    pub fn cache(self, manifest: impl AsRef<Path>) -> Batch {
+        Batch {
+            cache: Some(manifest.as_ref().to_path_buf()),
+            ..self
+        }
+    }
+
+    /// Renders every job even if the cache says it's up to date, and records
+    /// the new fingerprints.
+    pub fn force(self, force: bool) -> Batch {
+        Batch { force, ..self }
+    }
+
+    pub fn jobs(&self) -> &[BatchJob] {
+        &self.jobs
+    }
+
+    pub fn run(&self) -> Result<BatchSummary, Failure> {
+        self.run_with(|_| {})
+    }
+
+    /// Runs every job, calling `on_done` as each one finishes. A failed job,
+    /// even one that panics, doesn't stop the others; only a problem with
+    /// the cache manifest is returned as an error.
+    pub fn run_with(&self, on_done: impl Fn(&JobResult) + Sync) -> Result<BatchSummary, Failure> {
+        let start = Instant::now();
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new(vec![]);
+        let cache = match &self.cache {
+            Some(path) => Some(Mutex::new(RenderCache::load(path)?)),
+            None => None,
+        };
+
+        thread::scope(|scope| {
+            for _ in 0..self.workers.min(self.jobs.len()) {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let job = match self.jobs.get(index) {
+                        Some(job) => job,
+                        None => break,
+                    };
+
+                    let job_start = Instant::now();
+                    // rpt panics on some bad meshes, like ones with NaN
+                    // coordinates.
+                    let outcome =
+                        panic::catch_unwind(AssertUnwindSafe(|| self.render(job, cache.as_ref())))
+                            .unwrap_or_else(|panic| Err(panic_message(panic).into()));
+                    let result = JobResult {
+                        job: job.clone(),
+                        elapsed: job_start.elapsed(),
+                        skipped: matches!(outcome, Ok(true)),
+                        error: outcome.err().map(|e| e.to_string()),
+                    };
+
+                    on_done(&result);
+                    lock(&results).push((index, result));
+                });
+            }
+        });
+
+        if let Some(cache) = cache {
+            cache
+                .into_inner()
+                .unwrap_or_else(PoisonError::into_inner)
+                .save()?;
+        }
+
+        let mut results = results.into_inner().unwrap_or_else(PoisonError::into_inner);
+        results.sort_by_key(|(index, _)| *index);
+
+        Ok(BatchSummary {
+            results: results.into_iter().map(|(_, result)| result).collect(),
+            elapsed: start.elapsed(),
+        })
+    }
+
+    // Returns whether the job was skipped.
+    fn render(&self, job: &BatchJob, cache: Option<&Mutex<RenderCache>>) -> Result<bool, Failure> {
+        let mesh = Mesh::new(MeshSource::DynamicFile(PathFile::new(&job.input)?));
+        let model = self.preset.model(mesh)?;
+        let fingerprint = cache.map(|_| model.fingerprint()).transpose()?.flatten();
+
+        if let (Some(cache), Some(fingerprint)) = (cache, &fingerprint) {
+            if !self.force && lock(cache).is_fresh(&job.output, fingerprint) {
+                return Ok(true);
+            }
+        }
+
+        // Rendered in memory first, so a failed render leaves the last good
+        // image alone.
+        let mut png = vec![];
+        model.render(&mut png)?;
+
+        if let Some(parent) = job.output.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&job.output, png)?;
+
+        if let (Some(cache), Some(fingerprint)) = (cache, fingerprint) {
+            lock(cache).record(&job.output, fingerprint)?;
+        }
+
+        Ok(false)
+    }
+}
+
+// A panicking job can poison a lock, but whatever it guards is still
+// usable by the other jobs.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn panic_message(panic: Box<dyn std::any::Any + Send>) -> String {
+    let message = match panic.downcast::<String>() {
+        Ok(message) => *message,
+        Err(panic) => match panic.downcast::<&str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "unknown error".to_string(),
+        },
+    };
+
+    format!("Panicked: {}", message)
+}
+
+impl JobResult {
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+impl BatchSummary {
+    /// Rendered jobs, not counting skipped ones.
+    pub fn succeeded(&self) -> impl Iterator<Item = &JobResult> {
+        self.results
+            .iter()
+            .filter(|result| result.is_success() && !result.skipped)
+    }
+
+    pub fn skipped(&self) -> impl Iterator<Item = &JobResult> {
+        self.results.iter().filter(|result| result.skipped)
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = &JobResult> {
+        self.results.iter().filter(|result| !result.is_success())
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.failed().next().is_none()
+    }
+}
+
+impl fmt::Display for JobResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.error {
+            None if self.skipped => write!(f, "{} is up to date", self.job.output.display()),
+            None => write!(
+                f,
+                "{} -> {} ({:.1}s)",
+                self.job.input.display(),
+                self.job.output.display(),
+                self.elapsed.as_secs_f64()
+            ),
+            Some(error) => write!(f, "{} failed: {}", self.job.input.display(), error),
+        }
+    }
+}
+
+impl fmt::Display for BatchSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rendered: Vec<_> = self.succeeded().map(|result| result.elapsed).collect();
+
+        write!(
+            f,
+            "{} rendered, {} up to date, {} failed in {:.1}s",
+            rendered.len(),
+            self.skipped().count(),
+            self.failed().count(),
+            self.elapsed.as_secs_f64()
+        )?;
+
+        if let Some(slowest) = rendered.iter().max() {
+            write!(
+                f,
+                " (average {:.1}s, slowest {:.1}s)",
+                rendered.iter().map(Duration::as_secs_f64).sum::<f64>() / rendered.len() as f64,
+                slowest.as_secs_f64()
+            )?;
+        }
+
+        for failure in self.failed() {
+            write!(f, "\n  {}", failure)?;
+        }
+
+        Ok(())
+    }
+}
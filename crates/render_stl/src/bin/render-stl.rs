@@ -0,0 +1,371 @@
+use std::{fs, path::PathBuf, str::FromStr};
+
+use path_abs::PathFile;
+use project::{Failure, Nothing, Outcome};
+use render_stl::{
+    scene::SceneDescription, AdaptiveSampling, Angle, Annotations, Batch, Color, Denoise,
+    DeviationShading, Dimension, LayerLines, Lighting, Material, Mesh, MeshSource, Model, Preset,
+    RenderSettings, Shading, View,
+};
+use structopt::StructOpt;
+
+// Kept in the batch output directory.
+const CACHE_MANIFEST: &str = ".render-cache.json";
+
+/// Renders an STL mesh, or a TOML scene, to a PNG.
+#[derive(StructOpt)]
+#[structopt(name = "render-stl")]
+struct Options {
+    /// The STL file to render. With --batch, a directory or a glob like
+    /// "models/**/*.stl".
+    #[structopt(required_unless = "scene")]
+    input: Option<String>,
+
+    /// Render a TOML scene instead of a single mesh. The view, colour and
+    /// lighting options don't apply; the render options override the
+    /// scene's.
+    #[structopt(
+        long,
+        parse(from_os_str),
+        conflicts_with_all = &["input", "view", "color", "lighting", "background", "batch"]
+    )]
+    scene: Option<PathBuf>,
+
+    /// Where to write the PNG. With --batch, the directory to write PNGs
+    /// into, mirroring the input tree.
+    #[structopt(short, long, parse(from_os_str))]
+    output: PathBuf,
+
+    /// Render every mesh in a directory or glob.
+    #[structopt(long)]
+    batch: bool,
+
+    /// How many meshes to render at once with --batch. Defaults to the
+    /// number of CPUs.
+    #[structopt(short, long)]
+    jobs: Option<usize>,
+
+    /// With --batch, re-render meshes even if their cached image is up to
+    /// date.
+    #[structopt(long)]
+    force: bool,
+
+    /// With --batch, don't read or write the cache manifest.
+    #[structopt(long, conflicts_with = "force")]
+    no_cache: bool,
+
+    #[structopt(long)]
+    width: Option<u32>,
+
+    #[structopt(long)]
+    height: Option<u32>,
+
+    /// Samples per pixel.
+    #[structopt(long)]
+    samples: Option<u32>,
+
+    #[structopt(long)]
+    max_bounces: Option<u32>,
+
+    #[structopt(long)]
+    exposure: Option<f64>,
+
+    /// Keep adding samples until the image converges, using --samples as
+    /// the minimum.
+    #[structopt(long)]
+    adaptive: bool,
+
+    /// Smooth out noise with a filter guided by the surface normals.
+    #[structopt(long)]
+    denoise: bool,
+
+    /// One of front, back, left, right, top, bottom or iso.
+    #[structopt(long)]
+    view: Option<View>,
+
+    /// The mesh colour, like "#ff0000".
+    #[structopt(long)]
+    color: Option<Color>,
+
+    #[structopt(long, default_value = "0.5")]
+    roughness: f64,
+
+    #[structopt(long)]
+    metallic: Option<f64>,
+
+    /// One of studio, soft, flat or none.
+    #[structopt(long)]
+    lighting: Option<Lighting>,
+
+    /// The background colour, which also lights the scene.
+    #[structopt(long)]
+    background: Option<Color>,
+
+    /// Leave the background out of the image, for compositing.
+    #[structopt(long)]
+    transparent: bool,
+
+    /// Shade curved surfaces smoothly, keeping edges sharper than this many
+    /// degrees.
+    #[structopt(long, conflicts_with = "scene")]
+    smooth: Option<f64>,
+
+    /// Show FDM layer lines on the mesh's walls, this many mm apart.
+    #[structopt(long, conflicts_with = "scene")]
+    layer_lines: Option<f64>,
+
+    /// Colour the mesh by how far it is from this older version of it, and
+    /// print how much it changed.
+    #[structopt(long, parse(from_os_str), conflicts_with_all = &["scene", "batch"])]
+    diff: Option<PathBuf>,
+
+    /// Cut the model open and look down at the cut, like "z=5" or "-x=10".
+    #[structopt(long, conflicts_with = "batch")]
+    section: Option<Section>,
+
+    /// Flatten the perspective, so parallel edges stay parallel.
+    #[structopt(long, conflicts_with = "scene")]
+    orthographic: bool,
+
+    /// Draw the model's width, depth and height over the render. Replaces
+    /// any annotations in the scene.
+    #[structopt(long, conflicts_with = "batch")]
+    dimensions: bool,
+
+    /// Draw the distance between two points over the render, like
+    /// "0,0,0:10,0,0". Can be given more than once.
+    #[structopt(long, number_of_values = 1, conflicts_with = "batch")]
+    measure: Vec<Measure>,
+}
+
+struct Section {
+    axis: (i32, i32, i32),
+    height: f64,
+}
+
+impl FromStr for Section {
+    type Err = String;
+
+    fn from_str(section: &str) -> Result<Section, String> {
+        let invalid = || {
+            format!(
+                "Invalid section {:?}, expected AXIS=HEIGHT like z=5",
+                section
+            )
+        };
+        let (axis, height) = section.split_once('=').ok_or_else(invalid)?;
+
+        let axis = match axis.trim() {
+            "x" | "+x" => (1, 0, 0),
+            "y" | "+y" => (0, 1, 0),
+            "z" | "+z" => (0, 0, 1),
+            "-x" => (-1, 0, 0),
+            "-y" => (0, -1, 0),
+            "-z" => (0, 0, -1),
+            _ => return Err(invalid()),
+        };
+        let height = height.trim().parse().map_err(|_| invalid())?;
+
+        Ok(Section { axis, height })
+    }
+}
+
+struct Measure {
+    from: (f64, f64, f64),
+    to: (f64, f64, f64),
+}
+
+impl FromStr for Measure {
+    type Err = String;
+
+    fn from_str(measure: &str) -> Result<Measure, String> {
+        let invalid = || {
+            format!(
+                "Invalid measurement {:?}, expected X,Y,Z:X,Y,Z like 0,0,0:10,0,0",
+                measure
+            )
+        };
+        let point = |point: &str| -> Result<(f64, f64, f64), String> {
+            let coordinates = point
+                .split(',')
+                .map(|coordinate| coordinate.trim().parse().map_err(|_| invalid()))
+                .collect::<Result<Vec<f64>, _>>()?;
+
+            match coordinates[..] {
+                [x, y, z] => Ok((x, y, z)),
+                _ => Err(invalid()),
+            }
+        };
+        let (from, to) = measure.split_once(':').ok_or_else(invalid)?;
+
+        Ok(Measure {
+            from: point(from)?,
+            to: point(to)?,
+        })
+    }
+}
+
+impl Options {
+    fn settings(&self, settings: RenderSettings) -> RenderSettings {
+        RenderSettings {
+            width: self.width.unwrap_or(settings.width),
+            height: self.height.unwrap_or(settings.height),
+            samples: self.samples.unwrap_or(settings.samples),
+            max_bounces: self.max_bounces.unwrap_or(settings.max_bounces),
+            exposure: self.exposure.unwrap_or(settings.exposure),
+            adaptive: settings
+                .adaptive
+                .or_else(|| self.adaptive.then_some(AdaptiveSampling::DEFAULT)),
+            denoise: settings
+                .denoise
+                .or_else(|| self.denoise.then_some(Denoise::DEFAULT)),
+            transparent: settings.transparent || self.transparent,
+        }
+    }
+
+    fn preset(&self) -> Preset {
+        let defaults = Preset::DEFAULT;
+
+        let mut material = Material::specular(
+            self.color.unwrap_or_else(|| defaults.material.color()),
+            self.roughness,
+        );
+        if let Some(metallic) = self.metallic {
+            material = material.metallic(metallic);
+        }
+
+        Preset {
+            view: self.view.unwrap_or(defaults.view),
+            material,
+            lighting: self.lighting.unwrap_or(defaults.lighting),
+            background: self.background.unwrap_or(defaults.background),
+            settings: self.settings(defaults.settings),
+            smoothing: self.smooth.map(Angle::degrees),
+            layer_lines: self.layer_lines.map(LayerLines::new),
+            orthographic: self.orthographic,
+        }
+    }
+
+    fn model(&self) -> Result<Model, Failure> {
+        let model = if let Some(path) = &self.scene {
+            let mut scene = SceneDescription::load(path)?;
+            scene.render = self.settings(scene.render);
+
+            scene.into_model(path.parent().unwrap_or_else(|| ".".as_ref()))?
+        } else {
+            let input = self.input.as_ref().ok_or("No input mesh")?;
+            let file =
+                PathFile::new(input).map_err(|e| format!("Couldn't find {}: {}", input, e))?;
+
+            let mut mesh = Mesh::new(MeshSource::DynamicFile(file));
+
+            if let Some(reference) = &self.diff {
+                let reference = PathFile::new(reference)
+                    .map_err(|e| format!("Couldn't find {}: {}", reference.display(), e))?;
+                let reference = Mesh::new(MeshSource::DynamicFile(reference));
+
+                let diff = mesh.compare(&reference)?;
+                eprintln!(
+                    "Hausdorff distance {:.4}, mean {:.4}, furthest new vertex {:.4}, furthest old vertex {:.4}",
+                    diff.hausdorff, diff.mean, diff.max, diff.reverse_max
+                );
+
+                mesh = mesh.shading(Shading::Deviation(DeviationShading::new(&reference)?));
+            }
+
+            self.preset()
+                .model(mesh)
+                .map_err(|e| format!("Couldn't read {}: {}", input, e))?
+        };
+
+        let model = match &self.section {
+            Some(section) => model.cross_section(section.axis, section.height)?,
+            None => model,
+        };
+
+        Ok(match self.annotations() {
+            Some(annotations) => model.annotate(annotations),
+            None => model,
+        })
+    }
+
+    fn annotations(&self) -> Option<Annotations> {
+        if !self.dimensions && self.measure.is_empty() {
+            return None;
+        }
+
+        let mut annotations = Annotations::new();
+        if self.dimensions {
+            annotations = annotations.bounding_box();
+        }
+        for measure in &self.measure {
+            annotations = annotations.add_dimension(Dimension::new(measure.from, measure.to));
+        }
+
+        Some(annotations)
+    }
+
+    fn run(&self) -> Outcome {
+        if self.batch {
+            return self.run_batch();
+        }
+
+        // Rendered in memory first, so a failed render leaves any earlier
+        // image alone.
+        let mut png = vec![];
+        self.model()?.render(&mut png)?;
+
+        fs::write(&self.output, png)
+            .map_err(|e| format!("Couldn't write {}: {}", self.output.display(), e))?;
+
+        Ok(Nothing)
+    }
+
+    fn run_batch(&self) -> Outcome {
+        let input = self.input.as_ref().ok_or("No input directory or glob")?;
+
+        let mut batch = if PathBuf::from(input).is_dir() {
+            Batch::directory(input, &self.output)?
+        } else {
+            Batch::glob(input, &self.output)?
+        }
+        .preset(self.preset());
+
+        if let Some(jobs) = self.jobs {
+            batch = batch.workers(jobs);
+        }
+
+        if batch.jobs().is_empty() {
+            return Err(format!("No meshes found in {}", input).into());
+        }
+
+        if !self.no_cache {
+            batch = batch.cache(self.output.join(CACHE_MANIFEST));
+        }
+
+        let summary = batch
+            .force(self.force)
+            .run_with(|result| eprintln!("{}", result))?;
+        eprintln!("{}", summary);
+
+        if summary.is_success() {
+            Ok(Nothing)
+        } else {
+            Err(format!(
+                "{} of {} renders failed",
+                summary.failed().count(),
+                summary.results.len()
+            )
+            .into())
+        }
+    }
+}
+
+fn main() {
+    let options = Options::from_args();
+
+    if let Err(error) = options.run() {
+        eprintln!("render-stl: {}", error);
+        std::process::exit(1);
+    }
+}
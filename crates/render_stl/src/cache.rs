@@ -0,0 +1,121 @@
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use project::{Failure, Nothing, Outcome};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// A hash of everything that goes into a render: the mesh contents, its
+/// transforms and material, the camera, lights and render settings. Two
+/// renders with the same fingerprint produce the same image.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Fingerprint(String);
+
+impl Fingerprint {
+    pub fn of(bytes: impl AsRef<[u8]>) -> Fingerprint {
+        Fingerprint(
+            Sha256::digest(bytes.as_ref())
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect(),
+        )
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// A manifest of previous renders, saved as JSON, so that renders that
+/// wouldn't change can be skipped.
+#[derive(Debug)]
+pub struct RenderCache {
+    path: PathBuf,
+    entries: BTreeMap<String, CacheEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    fingerprint: Fingerprint,
+    /// The hash of the image, to notice when it's been edited or replaced.
+    output: Fingerprint,
+}
+
+impl RenderCache {
+    /// Loads the manifest at `path`, or starts an empty one if there isn't
+    /// one yet.
+    pub fn load(path: impl AsRef<Path>) -> Result<RenderCache, Failure> {
+        let path = path.as_ref().to_path_buf();
+
+        let entries = if path.exists() {
+            let source = fs::read_to_string(&path)
+                .map_err(|e| format!("Couldn't read cache {}: {}", path.display(), e))?;
+
+            serde_json::from_str(&source)
+                .map_err(|e| format!("Invalid cache {}: {}", path.display(), e))?
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(RenderCache { path, entries })
+    }
+
+    /// Whether `output` was last rendered with `fingerprint` and hasn't
+    /// changed since.
+    pub fn is_fresh(&self, output: &Path, fingerprint: &Fingerprint) -> bool {
+        let entry = match self.entries.get(&self.key(output)) {
+            Some(entry) if entry.fingerprint == *fingerprint => entry,
+            _ => return false,
+        };
+
+        match fs::read(output) {
+            Ok(image) => Fingerprint::of(image) == entry.output,
+            Err(_) => false,
+        }
+    }
+
+    /// Records that `output` has just been rendered with `fingerprint`.
+    pub fn record(&mut self, output: &Path, fingerprint: Fingerprint) -> Outcome {
+        let entry = CacheEntry {
+            fingerprint,
+            output: Fingerprint::of(fs::read(output)?),
+        };
+
+        self.entries.insert(self.key(output), entry);
+
+        Ok(Nothing)
+    }
+
+    pub fn save(&self) -> Outcome {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(&self.path, serde_json::to_string_pretty(&self.entries)?)
+            .map_err(|e| format!("Couldn't write cache {}: {}", self.path.display(), e))?;
+
+        Ok(Nothing)
+    }
+
+    // Outputs are keyed relative to the manifest, so the cache still works
+    // when it's run from another directory.
+    fn key(&self, output: &Path) -> String {
+        let root = self.path.parent().unwrap_or_else(|| Path::new(""));
+
+        output
+            .strip_prefix(root)
+            .unwrap_or(output)
+            .to_string_lossy()
+            .into_owned()
+    }
+}
@@ -1,28 +1,128 @@
-use crate::location::{position_to_rpt, Position};
+use rpt::glm::DVec3;
+use serde::{Deserialize, Serialize};
 
+use crate::{
+    angle::Angle,
+    location::{position_to_rpt, Position},
+};
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Camera {
     eye: Position,
     direction: Position,
     up: Position,
-    fov: f64,
+    fov: Angle,
     aperture: f64,
     focal_distance: f64,
 }
 
 // const SIXTH_CIRCLE: f64 = std::f64::consts::FRAC_PI_6;
 const EIGHTH_CIRCLE: f64 = std::f64::consts::FRAC_PI_8;
+// Narrow enough that parallel lines stay parallel to within a pixel or so.
+const ORTHOGRAPHIC_FOV: f64 = 0.01;
 
 impl Camera {
     const DEFAULT: Camera = Self {
         eye: (0.0, 0.0, 10.0),
         direction: (0.0, 0.0, -1.0),
         up: (0.0, 1.0, 0.0),
-        fov: EIGHTH_CIRCLE,
+        fov: Angle::radians(EIGHTH_CIRCLE),
         aperture: 0.0,
         focal_distance: 0.0,
     };
 }
 
+impl Camera {
+    pub fn eye(self, eye: Position) -> Camera {
+        Camera { eye, ..self }
+    }
+
+    pub fn direction(self, direction: Position) -> Camera {
+        Camera { direction, ..self }
+    }
+
+    /// Points the camera from its current eye position towards `target`.
+    pub fn look_at(self, target: Position) -> Camera {
+        let (x, y, z) = self.eye;
+        let (tx, ty, tz) = target;
+
+        Camera {
+            direction: (tx - x, ty - y, tz - z),
+            ..self
+        }
+    }
+
+    pub fn up(self, up: Position) -> Camera {
+        Camera { up, ..self }
+    }
+
+    pub fn fov(self, fov: Angle) -> Camera {
+        Camera { fov, ..self }
+    }
+
+    pub(crate) fn field_of_view(&self) -> Angle {
+        self.fov
+    }
+
+    pub fn aperture(self, aperture: f64) -> Camera {
+        Camera { aperture, ..self }
+    }
+
+    pub fn focal_distance(self, focal_distance: f64) -> Camera {
+        Camera {
+            focal_distance,
+            ..self
+        }
+    }
+
+    /// Flattens the perspective, as close to an orthographic view as rpt
+    /// can render. The field of view narrows and the camera backs away so
+    /// that the plane through `target` is framed the same.
+    pub fn orthographic(self, target: Position) -> Camera {
+        let direction = position_to_rpt(self.direction).normalize();
+        let eye = position_to_rpt(self.eye);
+        let depth = (position_to_rpt(target) - eye).dot(&direction);
+
+        let fov: f64 = self.fov.into();
+        let distance = depth * (fov / 2.0).tan() / (ORTHOGRAPHIC_FOV / 2.0).tan();
+        let eye = eye - direction * (distance - depth);
+        let focal_distance = if self.focal_distance > 0.0 {
+            self.focal_distance + distance - depth
+        } else {
+            self.focal_distance
+        };
+
+        Camera {
+            eye: (eye.x, eye.y, eye.z),
+            fov: Angle::radians(ORTHOGRAPHIC_FOV),
+            focal_distance,
+            ..self
+        }
+    }
+
+    /// Where `point` lands in a `width` × `height` render, in pixels from
+    /// the top left corner, or `None` if it's behind the camera.
+    pub fn project(&self, point: Position, width: u32, height: u32) -> Option<(f64, f64)> {
+        let camera: rpt::Camera = (*self).into();
+        let right: DVec3 = camera.direction.cross(&camera.up).normalize();
+        let offset = position_to_rpt(point) - camera.eye;
+
+        let depth = offset.dot(&camera.direction);
+        if depth <= f64::EPSILON {
+            return None;
+        }
+
+        // The inverse of rpt's `Camera::cast_ray` and pixel mapping.
+        let scale = (camera.fov / 2.0).tan().recip() / depth;
+        let (x, y) = (offset.dot(&right) * scale, offset.dot(&camera.up) * scale);
+        let (width, height) = (width as f64, height as f64);
+        let long = width.max(height);
+
+        Some(((x * long + width) / 2.0, (height - y * long) / 2.0))
+    }
+}
+
 // Cribbed from the implementation of Default for rpt::Camera.
 impl Default for Camera {
     fn default() -> Self {
@@ -32,11 +132,23 @@ impl Default for Camera {
 
 impl Into<rpt::Camera> for Camera {
     fn into(self) -> rpt::Camera {
+        // rpt expects a unit direction, and an up vector orthogonal to it.
+        let direction = position_to_rpt(self.direction).normalize();
+        // Looking straight along `up` leaves no way to tell which way is up,
+        // so fall back to +Y, or +Z if that's the direction too.
+        let up = [position_to_rpt(self.up), DVec3::y(), DVec3::z()]
+            .iter()
+            .filter_map(|up| up.try_normalize(f64::EPSILON))
+            .map(|up| up - up.dot(&direction) * direction)
+            .find(|up| up.norm() > 1e-6)
+            .unwrap_or_else(DVec3::x)
+            .normalize();
+
         rpt::Camera {
             eye: position_to_rpt(self.eye),
-            direction: position_to_rpt(self.direction),
-            up: position_to_rpt(self.up),
-            fov: self.fov,
+            direction,
+            up,
+            fov: self.fov.into(),
             aperture: self.aperture,
             focal_distance: self.focal_distance,
         }
@@ -0,0 +1,277 @@
+use rpt::{glm::DVec3, Triangle};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    color::Color, direction::Direction, location::Location, material::Material,
+    validation::is_degenerate,
+};
+
+// Slabs thinner than this between section vertices aren't worth capping.
+const SLAB_EPSILON: f64 = 1e-9;
+
+/// Cuts every mesh in a model open. Everything on the side `normal` points
+/// towards is discarded, and the cut is capped so solid parts don't look
+/// hollow.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClippingPlane {
+    pub point: Location,
+    pub normal: Direction,
+    /// The colour of the cut surface, or `None` to leave it open.
+    #[serde(default = "ClippingPlane::default_cap")]
+    pub cap: Option<Color>,
+}
+
+impl ClippingPlane {
+    pub const DEFAULT_CAP: Color = Color::hex(0xe03030);
+
+    pub fn new(point: impl Into<Location>, normal: impl Into<Direction>) -> ClippingPlane {
+        ClippingPlane {
+            point: point.into(),
+            normal: normal.into(),
+            cap: Self::default_cap(),
+        }
+    }
+
+    /// A plane at `height` along `axis`, discarding everything above it.
+    pub fn at(axis: impl Into<Direction>, height: f64) -> ClippingPlane {
+        let normal: Direction = axis.into();
+        let point: DVec3 = normal.into();
+        let point = point * height;
+
+        ClippingPlane::new(Location::new(point.x, point.y, point.z), normal)
+    }
+
+    pub fn cap(self, color: impl Into<Color>) -> ClippingPlane {
+        ClippingPlane {
+            cap: Some(color.into()),
+            ..self
+        }
+    }
+
+    pub fn uncapped(self) -> ClippingPlane {
+        ClippingPlane { cap: None, ..self }
+    }
+
+    fn default_cap() -> Option<Color> {
+        Some(Self::DEFAULT_CAP)
+    }
+
+    fn normal(&self) -> DVec3 {
+        self.normal.into()
+    }
+
+    // Positive on the discarded side.
+    fn distance(&self, point: &DVec3) -> f64 {
+        (point - self.point.to_offset()).dot(&self.normal())
+    }
+
+    /// The part of `triangles` behind the plane.
+    pub fn clip(&self, triangles: &[Triangle]) -> Vec<Triangle> {
+        triangles
+            .iter()
+            .flat_map(|triangle| self.clip_triangle(triangle))
+            .collect()
+    }
+
+    // Sutherland–Hodgman against a single plane, which leaves at most a
+    // quad. Vertex normals are interpolated along the cut edges.
+    fn clip_triangle(&self, triangle: &Triangle) -> Vec<Triangle> {
+        let corners = [
+            (triangle.v1, triangle.n1),
+            (triangle.v2, triangle.n2),
+            (triangle.v3, triangle.n3),
+        ];
+        let distances = corners.map(|(vertex, _)| self.distance(&vertex));
+
+        if distances.iter().all(|distance| *distance < 0.0) {
+            return vec![*triangle];
+        }
+
+        let mut kept = vec![];
+        for i in 0..3 {
+            let j = (i + 1) % 3;
+            let (from, to) = (corners[i], corners[j]);
+            let (d_from, d_to) = (distances[i], distances[j]);
+
+            if d_from < 0.0 {
+                kept.push(from);
+            }
+
+            if (d_from < 0.0) != (d_to < 0.0) {
+                let t = d_from / (d_from - d_to);
+                let normal = (from.1 + (to.1 - from.1) * t).normalize();
+                kept.push((from.0 + (to.0 - from.0) * t, normal));
+            }
+        }
+
+        (1..kept.len().saturating_sub(1))
+            .map(|i| {
+                let [(v1, n1), (v2, n2), (v3, n3)] = [kept[0], kept[i], kept[i + 1]];
+                Triangle {
+                    v1,
+                    v2,
+                    v3,
+                    n1,
+                    n2,
+                    n3,
+                }
+            })
+            .collect()
+    }
+
+    /// Triangles covering where the plane cuts through `triangles`, facing
+    /// along the normal. The cut outline is filled even-odd, so holes in
+    /// the section stay open; it's only solid for closed meshes.
+    pub fn cap_triangles(&self, triangles: &[Triangle]) -> Vec<Triangle> {
+        let normal = self.normal();
+        let origin = self.point.to_offset();
+        let helper = if normal.x.abs() < 0.9 {
+            DVec3::new(1.0, 0.0, 0.0)
+        } else {
+            DVec3::new(0.0, 1.0, 0.0)
+        };
+        let u = helper.cross(&normal).normalize();
+        let v = normal.cross(&u);
+
+        let to_plane = |point: DVec3| {
+            let offset = point - origin;
+            (offset.dot(&u), offset.dot(&v))
+        };
+        let to_world = |(x, y): (f64, f64)| origin + u * x + v * y;
+
+        let segments: Vec<_> = triangles
+            .iter()
+            .filter_map(|triangle| self.section(triangle))
+            .map(|(from, to)| (to_plane(from), to_plane(to)))
+            .collect();
+
+        fill(&segments)
+            .into_iter()
+            .map(|[a, b, c]| {
+                let mut triangle = Triangle::from_vertices(to_world(a), to_world(b), to_world(c));
+                triangle.n1 = normal;
+                triangle.n2 = normal;
+                triangle.n3 = normal;
+                triangle
+            })
+            .filter(|triangle| !is_degenerate(triangle))
+            .collect()
+    }
+
+    // Where the plane crosses a triangle, classifying vertices the same way
+    // as `clip_triangle` so the segments join up into closed outlines.
+    fn section(&self, triangle: &Triangle) -> Option<(DVec3, DVec3)> {
+        let vertices = [triangle.v1, triangle.v2, triangle.v3];
+        let distances = vertices.map(|vertex| self.distance(&vertex));
+
+        let crossings: Vec<DVec3> = (0..3)
+            .filter_map(|i| {
+                let j = (i + 1) % 3;
+                let (d_from, d_to) = (distances[i], distances[j]);
+
+                ((d_from < 0.0) != (d_to < 0.0)).then(|| {
+                    let t = d_from / (d_from - d_to);
+                    vertices[i] + (vertices[j] - vertices[i]) * t
+                })
+            })
+            .collect();
+
+        match crossings.as_slice() {
+            [from, to] if from != to => Some((*from, *to)),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn cap_object(&self, triangles: &[Triangle]) -> Option<rpt::Object> {
+        let color = self.cap?;
+        let cap = self.cap_triangles(triangles);
+
+        if cap.is_empty() {
+            return None;
+        }
+
+        let material = Material::specular(color, 0.8);
+
+        Some(rpt::Object::new(rpt::Mesh::new(cap)).material(material.into()))
+    }
+}
+
+/// Clips `triangles` against every plane.
+pub fn clip_all(planes: &[ClippingPlane], triangles: &[Triangle]) -> Vec<Triangle> {
+    planes.iter().fold(triangles.to_vec(), |triangles, plane| {
+        plane.clip(&triangles)
+    })
+}
+
+/// The caps for every plane, each trimmed by the other planes.
+pub(crate) fn cap_objects(planes: &[ClippingPlane], triangles: &[Triangle]) -> Vec<rpt::Object> {
+    planes
+        .iter()
+        .enumerate()
+        .filter_map(|(index, plane)| {
+            let others: Vec<_> = planes
+                .iter()
+                .enumerate()
+                .filter(|(other, _)| *other != index)
+                .map(|(_, plane)| *plane)
+                .collect();
+
+            plane.cap_object(&clip_all(&others, triangles))
+        })
+        .collect()
+}
+
+type Point = (f64, f64);
+
+// Sweep across the outline in y. Between two consecutive segment ends, the
+// segments that cross the slab alternate between entering and leaving the
+// section, so each pair of them bounds a trapezoid that's inside it.
+fn fill(segments: &[(Point, Point)]) -> Vec<[Point; 3]> {
+    let mut stops: Vec<f64> = segments
+        .iter()
+        .flat_map(|(from, to)| [from.1, to.1])
+        .collect();
+    stops.sort_by(f64::total_cmp);
+    stops.dedup();
+
+    let x_at = |(from, to): &(Point, Point), y: f64| {
+        from.0 + (to.0 - from.0) * (y - from.1) / (to.1 - from.1)
+    };
+
+    let mut triangles = vec![];
+    for slab in stops.windows(2) {
+        let (bottom, top) = (slab[0], slab[1]);
+        if top - bottom < SLAB_EPSILON {
+            continue;
+        }
+
+        let middle = (bottom + top) / 2.0;
+        let mut crossing: Vec<_> = segments
+            .iter()
+            .filter(|(from, to)| from.1.min(to.1) < middle && from.1.max(to.1) > middle)
+            .map(|segment| {
+                (
+                    x_at(segment, middle),
+                    x_at(segment, bottom),
+                    x_at(segment, top),
+                )
+            })
+            .collect();
+        crossing.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        for pair in crossing.chunks_exact(2) {
+            let (_, left_bottom, left_top) = pair[0];
+            let (_, right_bottom, right_top) = pair[1];
+
+            triangles.push([
+                (left_bottom, bottom),
+                (right_bottom, bottom),
+                (right_top, top),
+            ]);
+            triangles.push([(left_bottom, bottom), (right_top, top), (left_top, top)]);
+        }
+    }
+
+    triangles
+}
@@ -1,6 +1,12 @@
+use std::{fmt, str::FromStr};
+
 use amplify_derive::{From, Wrapper};
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
 
-#[derive(Default, From, Wrapper)]
+#[derive(Debug, Copy, Clone, Default, From, Wrapper)]
 pub struct Color {
     inner: u32,
 }
@@ -11,6 +17,14 @@ impl Color {
     pub const fn hex(hex: u32) -> Color {
         Color { inner: hex }
     }
+
+    pub const fn rgb(self) -> (u8, u8, u8) {
+        (
+            (self.inner >> 16) as u8,
+            (self.inner >> 8) as u8,
+            self.inner as u8,
+        )
+    }
 }
 
 // TODO
@@ -29,3 +43,62 @@ impl Into<rpt::Color> for Color {
         rpt::hex_color(self.inner)
     }
 }
+
+/// Parses `"#ff0000"` or `"ff0000"`.
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Color, String> {
+        let digits = value.strip_prefix('#').unwrap_or(value);
+
+        match u32::from_str_radix(digits, 16) {
+            Ok(hex) if digits.len() == 6 => Ok(Color::hex(hex)),
+            _ => Err(format!(
+                "Invalid colour {:?}, expected one like \"#ff0000\"",
+                value
+            )),
+        }
+    }
+}
+
+// Colours are written as `"#rrggbb"`. Integers like `0xff0000` are also
+// accepted.
+impl Serialize for Color {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&format_args!("#{:06x}", self.inner))
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ColorVisitor;
+
+        impl<'de> Visitor<'de> for ColorVisitor {
+            type Value = Color;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a colour like \"#ff0000\" or 0xff0000")
+            }
+
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<Color, E> {
+                if (0..=0xffffff).contains(&value) {
+                    Ok(Color::hex(value as u32))
+                } else {
+                    Err(E::invalid_value(de::Unexpected::Signed(value), &self))
+                }
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<Color, E> {
+                self.visit_i64(value.min(i64::MAX as u64) as i64)
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Color, E> {
+                value
+                    .parse()
+                    .map_err(|_| E::invalid_value(de::Unexpected::Str(value), &self))
+            }
+        }
+
+        deserializer.deserialize_any(ColorVisitor)
+    }
+}
@@ -0,0 +1,109 @@
+use rpt::image::RgbImage;
+use serde::{Deserialize, Serialize};
+
+use crate::passes::Passes;
+
+/// An edge-preserving blur, run on the rendered image before any
+/// post-processing. Neighbouring pixels are only averaged together when
+/// they show the same mesh with a similar normal, albedo and colour, so
+/// noise is smoothed without blurring across edges.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Denoise {
+    /// How far, in pixels, to look for neighbours.
+    pub radius: u32,
+    /// How different, from 0 to 1, colours can be before they stop being
+    /// averaged together.
+    pub color: f64,
+    /// The same for normals, by distance between unit vectors.
+    pub normal: f64,
+    /// The same for albedo, in linear intensity.
+    pub albedo: f64,
+}
+
+impl Denoise {
+    pub const DEFAULT: Denoise = Denoise {
+        radius: 3,
+        color: 0.15,
+        normal: 0.2,
+        albedo: 0.1,
+    };
+
+    pub(crate) fn apply(&self, image: &RgbImage, passes: &Passes) -> RgbImage {
+        let (width, height) = image.dimensions();
+        let radius = self.radius as i64;
+        let spatial = (radius as f64 / 2.0).max(0.5);
+
+        let falloff = |distance_squared: f64, sigma: f64| {
+            (-distance_squared / (2.0 * sigma * sigma).max(f64::EPSILON)).exp()
+        };
+        let distance_squared = |a: &[f32], b: &[f32]| -> f64 {
+            a.iter()
+                .zip(b)
+                .map(|(a, b)| (*a as f64 - *b as f64).powi(2))
+                .sum()
+        };
+        let color = |x: u32, y: u32| image.get_pixel(x, y).0.map(|c| c as f32 / 255.0);
+
+        let mut output = RgbImage::new(width, height);
+
+        for y in 0..height {
+            for x in 0..width {
+                let center = (y * width + x) as usize;
+                let center_color = color(x, y);
+
+                let mut total = [0.0; 3];
+                let mut weights = 0.0;
+
+                for dy in -radius..=radius {
+                    for dx in -radius..=radius {
+                        let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+
+                        if nx < 0 || ny < 0 || nx >= width as i64 || ny >= height as i64 {
+                            continue;
+                        }
+
+                        let (nx, ny) = (nx as u32, ny as u32);
+                        let neighbour = (ny * width + nx) as usize;
+
+                        if passes.object_id[neighbour] != passes.object_id[center] {
+                            continue;
+                        }
+
+                        let neighbour_color = color(nx, ny);
+                        let weight = falloff((dx * dx + dy * dy) as f64, spatial)
+                            * falloff(
+                                distance_squared(&neighbour_color, &center_color),
+                                self.color,
+                            )
+                            * falloff(
+                                distance_squared(&passes.normal[neighbour], &passes.normal[center]),
+                                self.normal,
+                            )
+                            * falloff(
+                                distance_squared(&passes.albedo[neighbour], &passes.albedo[center]),
+                                self.albedo,
+                            );
+
+                        for (total, channel) in total.iter_mut().zip(&neighbour_color) {
+                            *total += *channel as f64 * weight;
+                        }
+                        weights += weight;
+                    }
+                }
+
+                // The centre pixel always has a weight of one.
+                let pixel = total.map(|channel| (channel / weights * 255.0).round() as u8);
+                output.put_pixel(x, y, rpt::image::Rgb(pixel));
+            }
+        }
+
+        output
+    }
+}
+
+impl Default for Denoise {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
@@ -0,0 +1,378 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use project::Failure;
+use rpt::{glm::DVec3, BoundingBox, Triangle};
+use serde::{Serialize, Serializer};
+
+use crate::{
+    analysis::{to_position, vertex_key},
+    cache::Fingerprint,
+    clipping::{clip_all, ClippingPlane},
+    color::Color,
+    geometry::bounds,
+    location::Position,
+    material::Material,
+    mesh::{hash_triangles, Mesh},
+};
+
+const BANDS: usize = 8;
+const UNCHANGED: Color = Color::hex(0xb0b0b0);
+
+#[derive(Debug, Copy, Clone, Serialize)]
+pub struct VertexDeviation {
+    pub position: Position,
+    /// How far the vertex is from the closest point on the other mesh.
+    pub distance: f64,
+}
+
+/// How far one mesh is from another. Distances are measured from vertices
+/// to the closest point on the other surface, so they can miss changes in
+/// the middle of large faces.
+#[derive(Debug, Clone, Serialize)]
+pub struct MeshDiff {
+    /// The new mesh's distinct vertices.
+    pub vertices: Vec<VertexDeviation>,
+    pub mean: f64,
+    pub rms: f64,
+    /// The furthest any new vertex is from the reference.
+    pub max: f64,
+    /// The furthest any reference vertex is from the new mesh, which catches
+    /// parts that were removed.
+    pub reverse_max: f64,
+    /// The larger of `max` and `reverse_max`.
+    pub hausdorff: f64,
+}
+
+impl MeshDiff {
+    pub fn of(new: &[Triangle], reference: &[Triangle]) -> MeshDiff {
+        let vertices = deviations(new, &SurfaceIndex::new(reference));
+        let reverse = deviations(reference, &SurfaceIndex::new(new));
+
+        let count = vertices.len().max(1) as f64;
+        let mean = vertices.iter().map(|vertex| vertex.distance).sum::<f64>() / count;
+        let rms = (vertices
+            .iter()
+            .map(|vertex| vertex.distance * vertex.distance)
+            .sum::<f64>()
+            / count)
+            .sqrt();
+        let max = largest(&vertices);
+        let reverse_max = largest(&reverse);
+
+        MeshDiff {
+            vertices,
+            mean,
+            rms,
+            max,
+            reverse_max,
+            hausdorff: max.max(reverse_max),
+        }
+    }
+
+    /// Whether every vertex of each mesh is within `tolerance` of the other.
+    pub fn is_within(&self, tolerance: f64) -> bool {
+        self.hausdorff <= tolerance
+    }
+
+    pub fn to_json(&self) -> Result<String, Failure> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+fn deviations(triangles: &[Triangle], other: &SurfaceIndex) -> Vec<VertexDeviation> {
+    let mut seen = HashSet::new();
+
+    triangles
+        .iter()
+        .flat_map(|triangle| [triangle.v1, triangle.v2, triangle.v3])
+        .filter(|vertex| seen.insert(vertex_key(vertex)))
+        .map(|vertex| VertexDeviation {
+            position: to_position(&vertex),
+            distance: other.distance(&vertex),
+        })
+        .collect()
+}
+
+fn largest(vertices: &[VertexDeviation]) -> f64 {
+    vertices
+        .iter()
+        .map(|vertex| vertex.distance)
+        .fold(0.0, f64::max)
+}
+
+/// Colours a mesh by how far each face is from a reference mesh, from
+/// yellow for small changes to red at `scale`. Faces within `tolerance` of
+/// the reference are grey.
+#[derive(Clone)]
+pub struct DeviationShading {
+    reference: Arc<Vec<Triangle>>,
+    hash: Fingerprint,
+    tolerance: f64,
+    scale: Option<f64>,
+}
+
+impl DeviationShading {
+    /// Compares against `reference` as it would be rendered, with its
+    /// transforms applied.
+    pub fn new(reference: &Mesh) -> Result<DeviationShading, Failure> {
+        let triangles = reference.triangles()?;
+
+        Ok(DeviationShading {
+            hash: hash_triangles(&triangles),
+            reference: Arc::new(triangles),
+            tolerance: 1e-4,
+            scale: None,
+        })
+    }
+
+    pub fn tolerance(self, tolerance: f64) -> DeviationShading {
+        DeviationShading { tolerance, ..self }
+    }
+
+    /// The distance that's coloured fully red. Defaults to the largest
+    /// deviation in the mesh.
+    pub fn scale(self, scale: f64) -> DeviationShading {
+        DeviationShading {
+            scale: Some(scale),
+            ..self
+        }
+    }
+
+    // One mesh per band, like `OverhangReport::heat_map`. A face takes the
+    // largest deviation of its vertices.
+    pub(crate) fn heat_map(
+        &self,
+        triangles: &[Triangle],
+        clipping: &[ClippingPlane],
+    ) -> Vec<rpt::Object> {
+        let index = SurfaceIndex::new(&self.reference);
+        let mut distances = HashMap::new();
+        let mut distance = |vertex: &DVec3| {
+            *distances
+                .entry(vertex_key(vertex))
+                .or_insert_with(|| index.distance(vertex))
+        };
+
+        let faces: Vec<f64> = triangles
+            .iter()
+            .map(|triangle| {
+                distance(&triangle.v1)
+                    .max(distance(&triangle.v2))
+                    .max(distance(&triangle.v3))
+            })
+            .collect();
+
+        let scale = self
+            .scale
+            .unwrap_or_else(|| faces.iter().copied().fold(0.0, f64::max))
+            .max(self.tolerance);
+
+        let mut bands: Vec<Vec<Triangle>> = vec![vec![]; BANDS + 1];
+        for (triangle, deviation) in triangles.iter().zip(faces) {
+            let band = if deviation <= self.tolerance {
+                0
+            } else {
+                ((deviation / scale * BANDS as f64).ceil() as usize).clamp(1, BANDS)
+            };
+
+            bands[band].push(*triangle);
+        }
+
+        bands
+            .into_iter()
+            .enumerate()
+            .filter_map(|(band, group)| {
+                let group = clip_all(clipping, &group);
+
+                if group.is_empty() {
+                    return None;
+                }
+
+                let material = Material::specular(band_color(band), 0.5);
+
+                Some(rpt::Object::new(rpt::Mesh::new(group)).material(material.into()))
+            })
+            .collect()
+    }
+}
+
+// Only the reference's hash goes into fingerprints.
+impl Serialize for DeviationShading {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (&self.hash, self.tolerance, self.scale).serialize(serializer)
+    }
+}
+
+// Yellow through orange to red.
+fn band_color(band: usize) -> Color {
+    if band == 0 {
+        return UNCHANGED;
+    }
+
+    let t = (band - 1) as f64 / (BANDS - 1) as f64;
+    let green = (0xd0 as f64 * (1.0 - t)).round() as u32;
+
+    Color::hex(0xff0000 | green << 8)
+}
+
+/// Finds the closest point on a surface, using a grid of cells so only the
+/// triangles near a point are checked.
+pub(crate) struct SurfaceIndex<'a> {
+    triangles: &'a [Triangle],
+    origin: DVec3,
+    cell: f64,
+    size: [i64; 3],
+    cells: HashMap<[i64; 3], Vec<usize>>,
+}
+
+impl<'a> SurfaceIndex<'a> {
+    pub(crate) fn new(triangles: &'a [Triangle]) -> SurfaceIndex<'a> {
+        let BoundingBox { p_min, p_max } = bounds(triangles);
+        let extent = (p_max - p_min).max();
+        let cell = (extent / (triangles.len().max(1) as f64).cbrt()).max(1e-9);
+
+        let mut index = SurfaceIndex {
+            triangles,
+            origin: p_min,
+            cell,
+            size: [0; 3],
+            cells: HashMap::new(),
+        };
+
+        if triangles.is_empty() {
+            return index;
+        }
+
+        index.size = index.cell_of(&p_max).map(|last| last + 1);
+
+        for (number, triangle) in triangles.iter().enumerate() {
+            let BoundingBox { p_min, p_max } = bounds(std::slice::from_ref(triangle));
+            let (low, high) = (index.cell_of(&p_min), index.cell_of(&p_max));
+
+            for x in low[0]..=high[0] {
+                for y in low[1]..=high[1] {
+                    for z in low[2]..=high[2] {
+                        index.cells.entry([x, y, z]).or_default().push(number);
+                    }
+                }
+            }
+        }
+
+        index
+    }
+
+    fn cell_of(&self, point: &DVec3) -> [i64; 3] {
+        let offset = (point - self.origin) / self.cell;
+
+        [
+            offset.x.floor() as i64,
+            offset.y.floor() as i64,
+            offset.z.floor() as i64,
+        ]
+    }
+
+    /// Infinite if there are no triangles.
+    pub(crate) fn distance(&self, point: &DVec3) -> f64 {
+        if self.triangles.is_empty() {
+            return f64::INFINITY;
+        }
+
+        let center = self.cell_of(point);
+        // How far the point's cell is from the grid along each axis, so the
+        // rings before the furthest of these are empty.
+        let outside = |axis: usize| (-center[axis]).max(center[axis] - (self.size[axis] - 1));
+        let first_ring = (0..3).map(outside).max().unwrap_or(0).max(0);
+        // Past this ring, every cell in the grid has been checked.
+        let last_ring = (0..3)
+            .map(|axis| {
+                center[axis]
+                    .abs()
+                    .max((self.size[axis] - 1 - center[axis]).abs())
+            })
+            .max()
+            .unwrap_or(0);
+
+        let mut best = f64::INFINITY;
+
+        for ring in first_ring..=last_ring {
+            // The ring's cells along each axis, clipped to the grid.
+            let [xs, ys, zs] = [0, 1, 2].map(|axis| {
+                (center[axis] - ring).max(0)..=(center[axis] + ring).min(self.size[axis] - 1)
+            });
+
+            for x in xs {
+                for y in ys.clone() {
+                    let on_side = (x - center[0]).abs() == ring || (y - center[1]).abs() == ring;
+                    // Inside the ring's sides, only the top and bottom faces
+                    // are on the ring.
+                    let cap = [center[2] - ring, center[2] + ring];
+                    let zs: Vec<i64> = if on_side {
+                        zs.clone().collect()
+                    } else {
+                        cap.iter().copied().filter(|z| zs.contains(z)).collect()
+                    };
+
+                    for z in zs {
+                        for number in self.cells.get(&[x, y, z]).into_iter().flatten() {
+                            let closest = closest_point(point, &self.triangles[*number]);
+                            best = best.min((closest - point).norm());
+                        }
+                    }
+                }
+            }
+
+            // Anything in a cell further out is at least this far away.
+            if best <= ring as f64 * self.cell {
+                break;
+            }
+        }
+
+        best
+    }
+}
+
+// From Ericson, "Real-Time Collision Detection", 5.1.5.
+fn closest_point(p: &DVec3, triangle: &Triangle) -> DVec3 {
+    let (a, b, c) = (triangle.v1, triangle.v2, triangle.v3);
+    let (ab, ac, ap) = (b - a, c - a, p - a);
+
+    let (d1, d2) = (ab.dot(&ap), ac.dot(&ap));
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return a;
+    }
+
+    let bp = p - b;
+    let (d3, d4) = (ab.dot(&bp), ac.dot(&bp));
+    if d3 >= 0.0 && d4 <= d3 {
+        return b;
+    }
+
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+
+    let cp = p - c;
+    let (d5, d6) = (ab.dot(&cp), ac.dot(&cp));
+    if d6 >= 0.0 && d5 <= d6 {
+        return c;
+    }
+
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && d4 - d3 >= 0.0 && d5 - d6 >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+
+    let denominator = 1.0 / (va + vb + vc);
+    let (v, w) = (vb * denominator, vc * denominator);
+
+    a + ab * v + ac * w
+}
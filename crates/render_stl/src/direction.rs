@@ -1,25 +1,52 @@
 use amplify_derive::{From, Wrapper};
+use project::Failure;
 use rpt::glm::DVec3;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
-#[derive(Wrapper, From, Default)]
+#[derive(Debug, Copy, Clone, Wrapper, From, Default)]
 pub struct Direction {
     inner: DVec3,
 }
 
+impl Direction {
+    /// Fails for a zero vector, which doesn't point anywhere.
+    pub fn new(x: f64, y: f64, z: f64) -> Result<Direction, Failure> {
+        match DVec3::new(x, y, z).try_normalize(0.0) {
+            Some(inner) if inner.iter().all(|n| n.is_finite()) => Ok(Direction { inner }),
+            _ => Err(format!("A direction can't be zero, not [{}, {}, {}]", x, y, z).into()),
+        }
+    }
+}
+
+/// Panics for a zero vector. Use `Direction::new` to check first.
 impl Into<Direction> for (f64, f64, f64) {
     fn into(self) -> Direction {
         let (x, y, z) = self;
-        Direction {
-            inner: DVec3::new(x, y, z).normalize(),
-        }
+
+        Direction::new(x, y, z).unwrap_or_else(|e| panic!("{}", e))
     }
 }
 
+/// Panics for a zero vector.
 impl Into<Direction> for (i32, i32, i32) {
     fn into(self) -> Direction {
         let (x, y, z) = self;
-        Direction {
-            inner: DVec3::new(x as f64, y as f64, z as f64).normalize(),
-        }
+
+        (x as f64, y as f64, z as f64).into()
+    }
+}
+
+// Directions are written as `[x, y, z]`, and normalized when read.
+impl Serialize for Direction {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (self.inner.x, self.inner.y, self.inner.z).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Direction {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (x, y, z): (f64, f64, f64) = Deserialize::deserialize(deserializer)?;
+
+        Direction::new(x, y, z).map_err(de::Error::custom)
     }
 }
@@ -0,0 +1,164 @@
+use rpt::image::{Rgba, RgbaImage};
+
+pub(crate) const GLYPH_WIDTH: u32 = 5;
+pub(crate) const GLYPH_HEIGHT: u32 = 7;
+// A column between glyphs.
+pub(crate) const ADVANCE: u32 = GLYPH_WIDTH + 1;
+
+/// The size of `text` drawn at `scale`, in pixels.
+pub(crate) fn text_size(text: &str, scale: u32) -> (u32, u32) {
+    let glyphs = text.chars().count() as u32;
+
+    (
+        (glyphs * ADVANCE).saturating_sub(1) * scale,
+        GLYPH_HEIGHT * scale,
+    )
+}
+
+/// Draws `text` with its top left corner at `(left, top)`, each font pixel
+/// `scale` pixels square. Anything outside printable ASCII is drawn as `?`.
+pub(crate) fn draw_text(
+    image: &mut RgbaImage,
+    text: &str,
+    (left, top): (i64, i64),
+    scale: u32,
+    color: Rgba<u8>,
+) {
+    let scale = scale as i64;
+
+    for (index, character) in text.chars().enumerate() {
+        let glyph = glyph(character);
+        let x0 = left + index as i64 * ADVANCE as i64 * scale;
+
+        for (row, bits) in glyph.iter().enumerate() {
+            for column in 0..GLYPH_WIDTH {
+                if bits >> (GLYPH_WIDTH - 1 - column) & 1 == 0 {
+                    continue;
+                }
+
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        let x = x0 + column as i64 * scale + dx;
+                        let y = top + row as i64 * scale + dy;
+
+                        if (0..image.width() as i64).contains(&x)
+                            && (0..image.height() as i64).contains(&y)
+                        {
+                            image.put_pixel(x as u32, y as u32, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn glyph(character: char) -> &'static [u8; 7] {
+    let code = character as u32;
+    let code = if (0x20..0x7f).contains(&code) {
+        code
+    } else {
+        '?' as u32
+    };
+
+    &GLYPHS[(code - 0x20) as usize]
+}
+
+// Printable ASCII, one row of five pixels per byte, top to bottom.
+const GLYPHS: [[u8; 7]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+    [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04],
+    [0x0a, 0x0a, 0x0a, 0x00, 0x00, 0x00, 0x00],
+    [0x0a, 0x0a, 0x1f, 0x0a, 0x1f, 0x0a, 0x0a],
+    [0x04, 0x0f, 0x14, 0x0e, 0x05, 0x1e, 0x04],
+    [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03],
+    [0x0c, 0x12, 0x14, 0x08, 0x15, 0x12, 0x0d],
+    [0x04, 0x04, 0x04, 0x00, 0x00, 0x00, 0x00],
+    [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+    [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+    [0x00, 0x04, 0x15, 0x0e, 0x15, 0x04, 0x00],
+    [0x00, 0x04, 0x04, 0x1f, 0x04, 0x04, 0x00],
+    [0x00, 0x00, 0x00, 0x00, 0x0c, 0x04, 0x08],
+    [0x00, 0x00, 0x00, 0x1f, 0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c],
+    [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+    [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e],
+    [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e],
+    [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f],
+    [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e],
+    [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02],
+    [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e],
+    [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e],
+    [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+    [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e],
+    [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c],
+    [0x00, 0x0c, 0x0c, 0x00, 0x0c, 0x0c, 0x00],
+    [0x00, 0x0c, 0x0c, 0x00, 0x0c, 0x04, 0x08],
+    [0x02, 0x04, 0x08, 0x10, 0x08, 0x04, 0x02],
+    [0x00, 0x00, 0x1f, 0x00, 0x1f, 0x00, 0x00],
+    [0x08, 0x04, 0x02, 0x01, 0x02, 0x04, 0x08],
+    [0x0e, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04],
+    [0x0e, 0x11, 0x01, 0x0d, 0x15, 0x15, 0x0e],
+    [0x0e, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11],
+    [0x1e, 0x11, 0x11, 0x1e, 0x11, 0x11, 0x1e],
+    [0x0e, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0e],
+    [0x1c, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1c],
+    [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x1f],
+    [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x10],
+    [0x0e, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0f],
+    [0x11, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11],
+    [0x0e, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e],
+    [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0c],
+    [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+    [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1f],
+    [0x11, 0x1b, 0x15, 0x15, 0x11, 0x11, 0x11],
+    [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+    [0x0e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e],
+    [0x1e, 0x11, 0x11, 0x1e, 0x10, 0x10, 0x10],
+    [0x0e, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0d],
+    [0x1e, 0x11, 0x11, 0x1e, 0x14, 0x12, 0x11],
+    [0x0f, 0x10, 0x10, 0x0e, 0x01, 0x01, 0x1e],
+    [0x1f, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+    [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e],
+    [0x11, 0x11, 0x11, 0x11, 0x11, 0x0a, 0x04],
+    [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0a],
+    [0x11, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x11],
+    [0x11, 0x11, 0x11, 0x0a, 0x04, 0x04, 0x04],
+    [0x1f, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1f],
+    [0x0e, 0x08, 0x08, 0x08, 0x08, 0x08, 0x0e],
+    [0x00, 0x10, 0x08, 0x04, 0x02, 0x01, 0x00],
+    [0x0e, 0x02, 0x02, 0x02, 0x02, 0x02, 0x0e],
+    [0x04, 0x0a, 0x11, 0x00, 0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1f],
+    [0x08, 0x04, 0x02, 0x00, 0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x0e, 0x01, 0x0f, 0x11, 0x0f],
+    [0x10, 0x10, 0x16, 0x19, 0x11, 0x11, 0x1e],
+    [0x00, 0x00, 0x0e, 0x10, 0x10, 0x11, 0x0e],
+    [0x01, 0x01, 0x0d, 0x13, 0x11, 0x11, 0x0f],
+    [0x00, 0x00, 0x0e, 0x11, 0x1f, 0x10, 0x0e],
+    [0x06, 0x09, 0x08, 0x1c, 0x08, 0x08, 0x08],
+    [0x00, 0x0f, 0x11, 0x11, 0x0f, 0x01, 0x0e],
+    [0x10, 0x10, 0x16, 0x19, 0x11, 0x11, 0x11],
+    [0x04, 0x00, 0x0c, 0x04, 0x04, 0x04, 0x0e],
+    [0x02, 0x00, 0x06, 0x02, 0x02, 0x12, 0x0c],
+    [0x10, 0x10, 0x12, 0x14, 0x18, 0x14, 0x12],
+    [0x0c, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e],
+    [0x00, 0x00, 0x1a, 0x15, 0x15, 0x11, 0x11],
+    [0x00, 0x00, 0x16, 0x19, 0x11, 0x11, 0x11],
+    [0x00, 0x00, 0x0e, 0x11, 0x11, 0x11, 0x0e],
+    [0x00, 0x00, 0x1e, 0x11, 0x1e, 0x10, 0x10],
+    [0x00, 0x00, 0x0d, 0x13, 0x0f, 0x01, 0x01],
+    [0x00, 0x00, 0x16, 0x19, 0x10, 0x10, 0x10],
+    [0x00, 0x00, 0x0e, 0x10, 0x0e, 0x01, 0x1e],
+    [0x08, 0x08, 0x1c, 0x08, 0x08, 0x09, 0x06],
+    [0x00, 0x00, 0x11, 0x11, 0x11, 0x13, 0x0d],
+    [0x00, 0x00, 0x11, 0x11, 0x11, 0x0a, 0x04],
+    [0x00, 0x00, 0x11, 0x11, 0x15, 0x15, 0x0a],
+    [0x00, 0x00, 0x11, 0x0a, 0x04, 0x0a, 0x11],
+    [0x00, 0x00, 0x11, 0x11, 0x0f, 0x01, 0x0e],
+    [0x00, 0x00, 0x1f, 0x02, 0x04, 0x08, 0x1f],
+    [0x02, 0x04, 0x04, 0x08, 0x04, 0x04, 0x02],
+    [0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+    [0x08, 0x04, 0x04, 0x02, 0x04, 0x04, 0x08],
+    [0x00, 0x00, 0x08, 0x15, 0x02, 0x00, 0x00],
+];
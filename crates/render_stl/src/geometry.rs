@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+
+use rpt::{
+    glm::{self, DMat4, DVec3},
+    BoundingBox, Triangle,
+};
+
+use crate::analysis::vertex_key;
+
+pub fn bounds(triangles: &[Triangle]) -> BoundingBox {
+    triangles
+        .iter()
+        .fold(BoundingBox::default(), |bounds, triangle| BoundingBox {
+            p_min: glm::min2(
+                &bounds.p_min,
+                &glm::min3(&triangle.v1, &triangle.v2, &triangle.v3),
+            ),
+            p_max: glm::max2(
+                &bounds.p_max,
+                &glm::max3(&triangle.v1, &triangle.v2, &triangle.v3),
+            ),
+        })
+}
+
+pub fn transform_point(matrix: &DMat4, point: &DVec3) -> DVec3 {
+    (matrix * glm::vec4(point.x, point.y, point.z, 1.0)).xyz()
+}
+
+pub fn transform_triangle(matrix: &DMat4, triangle: &Triangle) -> Triangle {
+    let normals = glm::inverse_transpose(glm::mat4_to_mat3(matrix));
+    let normal = |n: &DVec3| (normals * n).normalize();
+
+    Triangle {
+        v1: transform_point(matrix, &triangle.v1),
+        v2: transform_point(matrix, &triangle.v2),
+        v3: transform_point(matrix, &triangle.v3),
+        n1: normal(&triangle.n1),
+        n2: normal(&triangle.n2),
+        n3: normal(&triangle.n3),
+    }
+}
+
+// The same (loose) box that `rpt::Transformed::bounding_box` produces: the
+// eight corners of the untransformed box, transformed.
+pub(crate) fn transform_bounds(
+    matrix: &DMat4,
+    BoundingBox { p_min, p_max }: BoundingBox,
+) -> BoundingBox {
+    let corners = [
+        glm::vec3(p_min.x, p_min.y, p_min.z),
+        glm::vec3(p_min.x, p_min.y, p_max.z),
+        glm::vec3(p_min.x, p_max.y, p_min.z),
+        glm::vec3(p_min.x, p_max.y, p_max.z),
+        glm::vec3(p_max.x, p_min.y, p_min.z),
+        glm::vec3(p_max.x, p_min.y, p_max.z),
+        glm::vec3(p_max.x, p_max.y, p_min.z),
+        glm::vec3(p_max.x, p_max.y, p_max.z),
+    ];
+
+    corners
+        .iter()
+        .map(|corner| transform_point(matrix, corner))
+        .fold(BoundingBox::default(), |bounds, corner| BoundingBox {
+            p_min: glm::min2(&bounds.p_min, &corner),
+            p_max: glm::max2(&bounds.p_max, &corner),
+        })
+}
+
+// Shared vertices and the faces that index them, for formats that store
+// meshes that way.
+pub(crate) fn index(triangles: &[Triangle]) -> (Vec<DVec3>, Vec<[usize; 3]>) {
+    let mut vertices = vec![];
+    let mut indices = HashMap::new();
+
+    let faces = triangles
+        .iter()
+        .map(|triangle| {
+            let mut index_of = |vertex: DVec3| {
+                *indices.entry(vertex_key(&vertex)).or_insert_with(|| {
+                    vertices.push(vertex);
+                    vertices.len() - 1
+                })
+            };
+
+            [
+                index_of(triangle.v1),
+                index_of(triangle.v2),
+                index_of(triangle.v3),
+            ]
+        })
+        .collect();
+
+    (vertices, faces)
+}
+
+pub(crate) fn area_vector(triangle: &Triangle) -> DVec3 {
+    (triangle.v2 - triangle.v1).cross(&(triangle.v3 - triangle.v1)) * 0.5
+}
@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+
+use rpt::{glm::DVec3, Triangle};
+use serde::{Deserialize, Serialize};
+
+use crate::{analysis::vertex_key, angle::Angle, validation::is_degenerate};
+
+// Faces flatter than this are left alone, since the layers show as
+// contours there rather than ridges.
+const MIN_STEEPNESS: f64 = 0.05;
+
+/// The look of an FDM print: every layer bulges out slightly, so walls show
+/// fine horizontal ridges. Layers stack along +Z from the lowest point of
+/// the mesh.
+///
+/// Walls are cut into two strips per layer, and the middle of each layer
+/// pushed out along the wall, so silhouettes show the ridges too. Normals
+/// are tilted to match, from down at the bottom of each layer to up at the
+/// top.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LayerLines {
+    pub layer_height: f64,
+    /// How far the middle of each layer bulges out of a vertical wall, as a
+    /// fraction of the layer height.
+    pub bulge: f64,
+    /// How far normals tilt at the edges of a layer, on a vertical wall.
+    pub relief: Angle,
+}
+
+impl LayerLines {
+    pub const DEFAULT: LayerLines = LayerLines {
+        layer_height: 0.2,
+        bulge: 0.15,
+        relief: Angle::radians(0.35),
+    };
+
+    pub fn new(layer_height: f64) -> LayerLines {
+        LayerLines {
+            layer_height,
+            ..LayerLines::DEFAULT
+        }
+    }
+
+    pub fn bulge(self, bulge: f64) -> LayerLines {
+        LayerLines { bulge, ..self }
+    }
+
+    pub fn relief(self, relief: impl Into<Angle>) -> LayerLines {
+        LayerLines {
+            relief: relief.into(),
+            ..self
+        }
+    }
+
+    /// `triangles` must already be where they'll be printed.
+    pub fn apply(&self, triangles: &[Triangle]) -> Vec<Triangle> {
+        if self.layer_height <= 0.0 || !self.layer_height.is_finite() {
+            return triangles.to_vec();
+        }
+
+        let bed = triangles
+            .iter()
+            .flat_map(|triangle| [triangle.v1.z, triangle.v2.z, triangle.v3.z])
+            .fold(f64::INFINITY, f64::min);
+        let relief: f64 = self.relief.into();
+        let relief = relief.tan();
+
+        let triangles: Vec<_> = triangles
+            .iter()
+            .flat_map(|triangle| self.layer_triangle(triangle, bed, relief))
+            .collect();
+
+        // Every face meeting at a point must push it the same way, or the
+        // mesh would crack open, so walls are pushed along their normals
+        // averaged at each point.
+        let mut outwards: HashMap<_, (DVec3, f64)> = HashMap::new();
+        for triangle in &triangles {
+            for (position, normal) in corners(triangle) {
+                let wall = DVec3::new(normal.x, normal.y, 0.0);
+                if wall.norm() >= MIN_STEEPNESS {
+                    let (sum, count) = outwards.entry(vertex_key(&position)).or_default();
+                    *sum += wall;
+                    *count += 1.0;
+                }
+            }
+        }
+
+        let depth = self.bulge * self.layer_height;
+        let push = |position: DVec3| match outwards.get(&vertex_key(&position)) {
+            Some((sum, count)) => {
+                // 0 at the edges of a layer, 1 in the middle.
+                let height = ((position.z - bed) / self.layer_height).rem_euclid(1.0);
+                let bump = 1.0 - (2.0 * height - 1.0).abs();
+
+                position + sum / *count * depth * bump
+            }
+            None => position,
+        };
+
+        triangles
+            .into_iter()
+            .map(|triangle| Triangle {
+                v1: push(triangle.v1),
+                v2: push(triangle.v2),
+                v3: push(triangle.v3),
+                ..triangle
+            })
+            .collect()
+    }
+
+    fn layer_triangle(&self, triangle: &Triangle, bed: f64, relief: f64) -> Vec<Triangle> {
+        let corners = vec![
+            Corner::new(triangle.v1, triangle.n1),
+            Corner::new(triangle.v2, triangle.n2),
+            Corner::new(triangle.v3, triangle.n3),
+        ];
+
+        let steepness = [triangle.n1, triangle.n2, triangle.n3]
+            .iter()
+            .map(|normal| normal.xy().norm())
+            .fold(0.0, f64::max);
+        if steepness < MIN_STEEPNESS || is_degenerate(triangle) {
+            return vec![*triangle];
+        }
+
+        // Strips are half a layer high, so there are points in the middle
+        // of each layer to push out.
+        let half = self.layer_height / 2.0;
+        let half_of = |z: f64| ((z - bed) / half).floor();
+        let (low, high) = corners
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), corner| {
+                (low.min(corner.position.z), high.max(corner.position.z))
+            });
+        let (first, last) = (half_of(low) as i64, half_of(high) as i64);
+
+        let mut strips = vec![];
+        let mut rest = corners;
+        for strip_index in first..=last {
+            let top = bed + (strip_index + 1) as f64 * half;
+            let (strip, above) = split(&rest, top);
+
+            if strip.len() >= 3 {
+                strips.push((strip_index.div_euclid(2), strip));
+            }
+            rest = above;
+
+            if rest.len() < 3 {
+                break;
+            }
+        }
+
+        strips
+            .into_iter()
+            .flat_map(|(layer, strip)| {
+                let bottom = bed + layer as f64 * self.layer_height;
+                let tilt = |corner: &Corner| {
+                    // -1 at the bottom of the layer, 1 at the top.
+                    let phase = (2.0 * (corner.position.z - bottom) / self.layer_height - 1.0)
+                        .clamp(-1.0, 1.0);
+                    let wall = corner.normal.xy().norm();
+                    let normal = corner.normal + DVec3::new(0.0, 0.0, phase * relief * wall);
+
+                    normal.normalize()
+                };
+
+                (1..strip.len() - 1)
+                    .map(|i| Triangle {
+                        v1: strip[0].position,
+                        v2: strip[i].position,
+                        v3: strip[i + 1].position,
+                        n1: tilt(&strip[0]),
+                        n2: tilt(&strip[i]),
+                        n3: tilt(&strip[i + 1]),
+                    })
+                    .filter(|triangle| !is_degenerate(triangle))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
+
+fn corners(triangle: &Triangle) -> [(DVec3, DVec3); 3] {
+    [
+        (triangle.v1, triangle.n1),
+        (triangle.v2, triangle.n2),
+        (triangle.v3, triangle.n3),
+    ]
+}
+
+impl Default for LayerLines {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+#[derive(Copy, Clone)]
+struct Corner {
+    position: DVec3,
+    normal: DVec3,
+}
+
+impl Corner {
+    fn new(position: DVec3, normal: DVec3) -> Corner {
+        Corner { position, normal }
+    }
+
+    fn towards(&self, other: &Corner, t: f64) -> Corner {
+        Corner {
+            position: self.position + (other.position - self.position) * t,
+            normal: (self.normal + (other.normal - self.normal) * t).normalize(),
+        }
+    }
+}
+
+// Splits a convex polygon at height `z` into the parts below and above.
+fn split(polygon: &[Corner], z: f64) -> (Vec<Corner>, Vec<Corner>) {
+    let (mut below, mut above) = (vec![], vec![]);
+
+    for i in 0..polygon.len() {
+        let (from, to) = (&polygon[i], &polygon[(i + 1) % polygon.len()]);
+        let (from_below, to_below) = (from.position.z < z, to.position.z < z);
+
+        if from_below {
+            below.push(*from);
+        } else {
+            above.push(*from);
+        }
+
+        if from_below != to_below {
+            // Always from the lower end, so faces sharing the edge cross it
+            // at exactly the same point.
+            let (lower, upper) = if from_below { (from, to) } else { (to, from) };
+            let t = (z - lower.position.z) / (upper.position.z - lower.position.z);
+            let crossing = lower.towards(upper, t);
+
+            below.push(crossing);
+            above.push(crossing);
+        }
+    }
+
+    (below, above)
+}
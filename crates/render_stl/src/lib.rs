@@ -1,19 +1,78 @@
+#![allow(clippy::from_over_into, clippy::needless_update)]
+
+pub mod adaptive;
+pub mod analysis;
 pub mod angle;
+pub mod annotate;
+pub mod batch;
+pub mod cache;
 pub mod camera;
+pub mod clipping;
 pub mod color;
+pub mod denoise;
+pub mod diff;
 pub mod direction;
+mod font;
+pub mod geometry;
+pub mod layer_lines;
 pub mod light_source;
+pub mod lighting;
 pub mod location;
 pub mod material;
 pub mod mesh;
+pub mod metadata;
 pub mod model;
+pub mod obj;
+pub mod orientation;
+pub mod overhang;
+pub mod part;
+pub mod passes;
+pub mod ply;
+pub mod postprocess;
+pub mod preset;
+pub mod printer;
+pub mod repair;
 pub mod rotation;
+pub mod scene;
+pub mod slice;
+pub mod smooth;
+pub mod stl;
+pub mod three_mf;
+pub mod validation;
+pub mod view;
+
+pub use crate::mesh::{Mesh, MeshSource, Shading};
 
-pub use crate::mesh::{Mesh, MeshSource};
+pub use crate::camera::Camera;
 
 pub use crate::{
-    angle::Angle, color::Color, light_source::LightSource, location::Location, material::Material,
-    model::Model, rotation::Rotation,
+    adaptive::AdaptiveSampling,
+    analysis::MeshAnalysis,
+    angle::Angle,
+    annotate::{Annotations, Dimension},
+    batch::{Batch, BatchSummary},
+    clipping::ClippingPlane,
+    color::Color,
+    denoise::Denoise,
+    diff::{DeviationShading, MeshDiff},
+    layer_lines::LayerLines,
+    light_source::LightSource,
+    lighting::Lighting,
+    location::Location,
+    material::Material,
+    metadata::RenderMetadata,
+    model::{Model, RenderSettings, RenderStats, RenderedImage},
+    overhang::OverhangSettings,
+    part::Part,
+    passes::{Pass, Passes},
+    postprocess::{PostProcess, PostProcessStep},
+    preset::Preset,
+    printer::PrinterProfile,
+    rotation::Rotation,
+    slice::{SlicePreview, Slices},
+    stl::StlFormat,
+    validation::ValidationReport,
+    view::View,
 };
 
 // struct Project {
@@ -1,11 +1,48 @@
+use serde::{Deserialize, Serialize};
+
 use crate::{color::Color, direction::Direction, location::Location};
 
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(from = "LightSourceFields", into = "LightSourceFields")]
 pub enum LightSource {
     Ambient(Color),
     Directional(Color, Direction),
     Point(Color, Location),
 }
 
+// How a `LightSource` is written: `{ kind = "point", color, location }`.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+enum LightSourceFields {
+    Ambient { color: Color },
+    Directional { color: Color, direction: Direction },
+    Point { color: Color, location: Location },
+}
+
+impl From<LightSourceFields> for LightSource {
+    fn from(fields: LightSourceFields) -> Self {
+        match fields {
+            LightSourceFields::Ambient { color } => LightSource::Ambient(color),
+            LightSourceFields::Directional { color, direction } => {
+                LightSource::Directional(color, direction)
+            }
+            LightSourceFields::Point { color, location } => LightSource::Point(color, location),
+        }
+    }
+}
+
+impl From<LightSource> for LightSourceFields {
+    fn from(light: LightSource) -> Self {
+        match light {
+            LightSource::Ambient(color) => LightSourceFields::Ambient { color },
+            LightSource::Directional(color, direction) => {
+                LightSourceFields::Directional { color, direction }
+            }
+            LightSource::Point(color, location) => LightSourceFields::Point { color, location },
+        }
+    }
+}
+
 impl LightSource {
     pub fn ambient(color: impl Into<Color>) -> LightSource {
         LightSource::Ambient(color.into())
@@ -0,0 +1,82 @@
+use std::{fmt, str::FromStr};
+
+use crate::{color::Color, light_source::LightSource};
+
+/// A preset set of lights, arranged for a Z-up mesh seen from the front.
+/// The lights are all directional or ambient, so they work at any scale.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub enum Lighting {
+    #[default]
+    /// A bright key light from the upper left, a dimmer fill from the right
+    /// and a rim light from behind.
+    Studio,
+    /// One light from above and plenty of ambient, for low contrast.
+    Soft,
+    /// Ambient light only, so every face shows its flat colour.
+    Flat,
+    /// No lights. The background is the only light source.
+    None,
+}
+
+impl Lighting {
+    pub const ALL: [Lighting; 4] = [
+        Lighting::Studio,
+        Lighting::Soft,
+        Lighting::Flat,
+        Lighting::None,
+    ];
+
+    pub fn lights(self) -> Vec<LightSource> {
+        match self {
+            Lighting::Studio => vec![
+                LightSource::directional(Color::hex(0xd0d0d0), (1.0, 1.0, -1.0)),
+                LightSource::directional(Color::hex(0x606060), (-1.0, 1.0, -0.3)),
+                LightSource::directional(Color::hex(0x404040), (0.0, -1.0, -0.5)),
+                LightSource::ambient(Color::hex(0x202020)),
+            ],
+            Lighting::Soft => vec![
+                LightSource::directional(Color::hex(0x909090), (0.3, 0.5, -1.0)),
+                LightSource::ambient(Color::hex(0x505050)),
+            ],
+            Lighting::Flat => vec![LightSource::ambient(Color::hex(0x909090))],
+            Lighting::None => vec![],
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Lighting::Studio => "studio",
+            Lighting::Soft => "soft",
+            Lighting::Flat => "flat",
+            Lighting::None => "none",
+        }
+    }
+}
+
+impl fmt::Display for Lighting {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Lighting {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Lighting, String> {
+        Lighting::ALL
+            .iter()
+            .copied()
+            .find(|lighting| lighting.name() == name)
+            .ok_or_else(|| {
+                let names: Vec<_> = Lighting::ALL
+                    .iter()
+                    .map(|lighting| lighting.name())
+                    .collect();
+                format!(
+                    "Unknown lighting {:?}, expected one of {}",
+                    name,
+                    names.join(", ")
+                )
+            })
+    }
+}
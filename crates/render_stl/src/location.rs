@@ -1,11 +1,13 @@
 use amplify_derive::{From, Wrapper};
 use rpt::glm::DVec3;
+use serde::{Deserialize, Serialize};
 
 pub type Position = (f64, f64, f64);
 pub type RptPosition = rpt::glm::TVec3<f64>;
 pub type RptOffset = rpt::glm::DVec3;
 
-#[derive(Default, Wrapper, From)]
+#[derive(Debug, Copy, Clone, Default, Wrapper, From, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct Location {
     inner: Position,
 }
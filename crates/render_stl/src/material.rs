@@ -1,5 +1,9 @@
+use serde::{Deserialize, Serialize};
+
 use crate::color::Color;
 
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Material {
     color: Color,
     refraction: f64,
@@ -22,6 +26,29 @@ impl Material {
             transparent: false,
         }
     }
+
+    pub const fn color(&self) -> Color {
+        self.color
+    }
+
+    pub const fn refraction(self, refraction: f64) -> Material {
+        Material { refraction, ..self }
+    }
+
+    pub const fn metallic(self, metallic: f64) -> Material {
+        Material { metallic, ..self }
+    }
+
+    pub const fn emittance(self, emittance: f64) -> Material {
+        Material { emittance, ..self }
+    }
+
+    pub const fn transparent(self, transparent: bool) -> Material {
+        Material {
+            transparent,
+            ..self
+        }
+    }
 }
 
 impl Into<rpt::Material> for Material {
@@ -1,13 +1,40 @@
-use std::fs::File;
+use std::{
+    borrow::Cow,
+    io::{Read, Write},
+    path::Path,
+    sync::Arc,
+};
 
 use path_abs::{FileRead, PathAbs, PathFile};
-use project::Failure;
+use project::{Failure, Outcome};
 use rpt::{
-    glm::{vec3, TVec3},
-    Scene, SceneAdd, Transformable,
+    glm::{self, vec3, DMat4, TVec3},
+    Scene, SceneAdd, Transformable, Triangle,
 };
+use serde::Serialize;
 
-use crate::{angle::Angle, location::Location, material::Material, rotation::Rotation};
+use crate::{
+    analysis::MeshAnalysis,
+    angle::Angle,
+    cache::Fingerprint,
+    clipping::{cap_objects, clip_all, ClippingPlane},
+    diff::{DeviationShading, MeshDiff},
+    geometry::{bounds, transform_point, transform_triangle},
+    layer_lines::LayerLines,
+    location::Location,
+    material::Material,
+    metadata::MeshMetadata,
+    orientation::best_orientation,
+    overhang::{OverhangReport, OverhangSettings},
+    part::Part,
+    printer::{BuildVolumeFit, PrinterProfile},
+    repair::{repair, RepairReport},
+    rotation::Rotation,
+    slice::Slices,
+    smooth::smooth_normals,
+    stl::{read_stl, write_stl, StlFormat},
+    validation::ValidationReport,
+};
 
 pub enum Transformation {
     Rotate(f64),
@@ -16,7 +43,14 @@ pub enum Transformation {
 
 pub enum MeshSource {
     DynamicFile(PathFile),
+    /// A path, resolved when the mesh is read. To embed the file in the
+    /// binary, use `Bytes` with `include_bytes!`.
     StaticFile(&'static str),
+    /// The contents of a binary or ASCII STL.
+    Bytes(Cow<'static, [u8]>),
+    Triangles(Vec<Triangle>),
+    /// Geometry built on demand, every time the mesh is read.
+    Generated(Arc<dyn Fn() -> Vec<Triangle> + Send + Sync>),
 }
 
 impl MeshSource {
@@ -24,22 +58,108 @@ impl MeshSource {
         MeshSource::StaticFile(filename)
     }
 
-    fn load(self) -> Result<rpt::Mesh, Failure> {
+    /// STL contents, like `MeshSource::bytes(include_bytes!("blok.stl"))`.
+    pub const fn bytes(bytes: &'static [u8]) -> MeshSource {
+        MeshSource::Bytes(Cow::Borrowed(bytes))
+    }
+
+    /// Reads a whole STL from `source` straight away, since the mesh may be
+    /// read more than once.
+    pub fn read(mut source: impl Read) -> Result<MeshSource, Failure> {
+        let mut bytes = vec![];
+        source.read_to_end(&mut bytes)?;
+
+        Ok(MeshSource::Bytes(bytes.into()))
+    }
+
+    pub fn generated(generate: impl Fn() -> Vec<Triangle> + Send + Sync + 'static) -> MeshSource {
+        MeshSource::Generated(Arc::new(generate))
+    }
+
+    pub fn triangles(&self) -> Result<Vec<Triangle>, Failure> {
+        let path = match self {
+            MeshSource::DynamicFile(path) => PathAbs::new(path)?,
+            MeshSource::StaticFile(file) => PathAbs::new(file)?,
+            MeshSource::Bytes(bytes) => return Ok(read_stl(bytes.as_ref())?),
+            MeshSource::Triangles(triangles) => return Ok(triangles.clone()),
+            MeshSource::Generated(generate) => return Ok(generate()),
+        };
+
+        Ok(read_stl(FileRead::open(path)?)?)
+    }
+
+    /// The file the mesh is read from, if any.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            MeshSource::DynamicFile(path) => Some(path.as_ref()),
+            MeshSource::StaticFile(file) => Some(Path::new(file)),
+            _ => None,
+        }
+    }
+
+    /// A hash of the STL's bytes, or of the triangles themselves.
+    pub fn content_hash(&self) -> Result<Fingerprint, Failure> {
         let path = match self {
             MeshSource::DynamicFile(path) => PathAbs::new(path)?,
             MeshSource::StaticFile(file) => PathAbs::new(file)?,
+            MeshSource::Bytes(bytes) => return Ok(Fingerprint::of(bytes)),
+            MeshSource::Triangles(triangles) => return Ok(hash_triangles(triangles)),
+            MeshSource::Generated(generate) => return Ok(hash_triangles(&generate())),
         };
 
-        let file: File = FileRead::open(path)?.into();
-        Ok(rpt::load_stl(file)?)
+        Ok(Fingerprint::of(std::fs::read(path)?))
     }
 }
+
+impl From<Vec<Triangle>> for MeshSource {
+    fn from(triangles: Vec<Triangle>) -> Self {
+        MeshSource::Triangles(triangles)
+    }
+}
+
+// Normals are included, since they change how the mesh is shaded.
+pub(crate) fn hash_triangles(triangles: &[Triangle]) -> Fingerprint {
+    let bytes: Vec<u8> = triangles
+        .iter()
+        .flat_map(|triangle| {
+            [
+                triangle.v1,
+                triangle.v2,
+                triangle.v3,
+                triangle.n1,
+                triangle.n2,
+                triangle.n3,
+            ]
+        })
+        .flat_map(|vertex| [vertex.x, vertex.y, vertex.z])
+        .flat_map(f64::to_le_bytes)
+        .collect();
+
+    Fingerprint::of(bytes)
+}
+
+#[derive(Clone, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Shading {
+    #[default]
+    Material,
+    /// Colour each face by how badly it overhangs, ignoring the material.
+    Overhang(OverhangSettings),
+    /// Colour each face by how far it is from another mesh, ignoring the
+    /// material.
+    Deviation(DeviationShading),
+}
+
 pub struct Mesh {
     source: MeshSource,
     material: Material,
+    shading: Shading,
     scale: f64,
     rotate: Rotation,
     translate: Location,
+    drop_to_bed: bool,
+    smoothing: Option<Angle>,
+    layer_lines: Option<LayerLines>,
 }
 
 impl Mesh {
@@ -47,9 +167,13 @@ impl Mesh {
         Mesh {
             source,
             material: Material::DEFAULT,
+            shading: Shading::Material,
             scale: 1.0,
             translate: Location::ORIGIN,
             rotate: Rotation::all(Angle::degrees(0.0)),
+            drop_to_bed: false,
+            smoothing: None,
+            layer_lines: None,
         }
     }
 
@@ -69,10 +193,188 @@ impl Mesh {
         self
     }
 
+    /// After all other transforms, move the mesh along Z so that its lowest
+    /// point sits at Z=0.
+    pub fn drop_to_bed(mut self) -> Mesh {
+        self.drop_to_bed = true;
+        self
+    }
+
     pub fn material(mut self, material: impl Into<Material>) -> Mesh {
         self.material = material.into();
         self
     }
+
+    pub fn shading(mut self, shading: Shading) -> Mesh {
+        self.shading = shading;
+        self
+    }
+
+    /// Renders curved surfaces smoothly, keeping edges where faces meet at
+    /// more than `crease` sharp. See `smooth::smooth_normals`.
+    pub fn smooth(mut self, crease: impl Into<Angle>) -> Mesh {
+        self.smoothing = Some(crease.into());
+        self
+    }
+
+    /// Shades the mesh as if FDM printed, with a ridge per layer on its
+    /// walls.
+    pub fn layer_lines(mut self, layer_lines: LayerLines) -> Mesh {
+        self.layer_lines = Some(layer_lines);
+        self
+    }
+
+    /// The source triangles, with this mesh's scale, rotation and
+    /// translation applied.
+    pub fn triangles(&self) -> Result<Vec<Triangle>, Failure> {
+        let triangles = self.source.triangles()?;
+        let matrix = self.matrix(&triangles);
+
+        Ok(triangles
+            .iter()
+            .map(|triangle| transform_triangle(&matrix, triangle))
+            .collect())
+    }
+
+    /// Writes the mesh as it would be rendered, with its transforms baked
+    /// into the triangles.
+    pub fn write_stl(&self, target: &mut impl Write, format: StlFormat) -> Outcome {
+        write_stl(&self.triangles()?, target, &format)
+    }
+
+    /// The transformed triangles as a part named `name`, coloured with this
+    /// mesh's material, for the multi-object writers.
+    pub fn part(&self, name: impl Into<String>) -> Result<Part, Failure> {
+        Ok(Part::new(name, self.triangles()?).color(self.material.color()))
+    }
+
+    pub fn analyze(&self) -> Result<MeshAnalysis, Failure> {
+        Ok(MeshAnalysis::of(&self.triangles()?))
+    }
+
+    pub fn validate(&self) -> Result<ValidationReport, Failure> {
+        Ok(ValidationReport::of(&self.triangles()?))
+    }
+
+    pub fn overhangs(&self, settings: &OverhangSettings) -> Result<OverhangReport, Failure> {
+        Ok(OverhangReport::of(&self.triangles()?, settings))
+    }
+
+    /// How far this mesh is from `reference`, both as they would be
+    /// rendered.
+    pub fn compare(&self, reference: &Mesh) -> Result<MeshDiff, Failure> {
+        Ok(MeshDiff::of(&self.triangles()?, &reference.triangles()?))
+    }
+
+    /// Cuts the transformed mesh into layers. See `slice`.
+    pub fn slice(&self, layer_height: f64) -> Result<Slices, Failure> {
+        Slices::of(&self.triangles()?, layer_height)
+    }
+
+    /// Repairs the source triangles (see `repair::repair`), keeping this
+    /// mesh's transforms and material.
+    pub fn repair(self, tolerance: f64) -> Result<(Mesh, RepairReport), Failure> {
+        let (triangles, report) = repair(&self.source.triangles()?, tolerance)?;
+
+        Ok((
+            Mesh {
+                source: MeshSource::Triangles(triangles),
+                ..self
+            },
+            report,
+        ))
+    }
+
+    /// Picks the rotation that rests the most area on the bed while leaving
+    /// the least unsupported overhang. Pass the result to `rotate`, usually
+    /// followed by `drop_to_bed`.
+    pub fn best_orientation(&self, settings: &OverhangSettings) -> Result<Rotation, Failure> {
+        Ok(best_orientation(&self.source.triangles()?, settings).rotation)
+    }
+
+    /// Whether the mesh, as it would be rendered, fits `printer`, and
+    /// which quarter-turn rotations would make it fit.
+    pub fn fits(&self, printer: &PrinterProfile) -> Result<BuildVolumeFit, Failure> {
+        let source = self.source.triangles()?;
+        let transformed = self.triangles()?;
+
+        Ok(BuildVolumeFit::of(printer, &transformed, |rotation| {
+            let matrix = self.matrix_with(rotation, &source);
+
+            source
+                .iter()
+                .map(|triangle| transform_triangle(&matrix, triangle))
+                .collect()
+        }))
+    }
+
+    fn matrix(&self, triangles: &[Triangle]) -> DMat4 {
+        self.matrix_with(self.rotate, triangles)
+    }
+
+    fn matrix_with(&self, rotation: Rotation, triangles: &[Triangle]) -> DMat4 {
+        let scaled = glm::scale(&glm::identity(), &scale_all(self.scale));
+        let rotated = rotation.rotate_matrix(scaled, bounds(triangles));
+
+        let matrix = glm::translate(&glm::identity(), &self.translate.to_offset()) * rotated;
+
+        if self.drop_to_bed {
+            let lowest = triangles
+                .iter()
+                .flat_map(|triangle| [triangle.v1, triangle.v2, triangle.v3])
+                .map(|vertex| transform_point(&matrix, &vertex).z)
+                .fold(f64::INFINITY, f64::min);
+
+            if lowest.is_finite() {
+                return glm::translate(&glm::identity(), &vec3(0.0, 0.0, -lowest)) * matrix;
+            }
+        }
+
+        matrix
+    }
+}
+
+// What `Model::fingerprint` records about a mesh.
+#[derive(Serialize)]
+pub(crate) struct MeshSummary {
+    source: Fingerprint,
+    material: Material,
+    shading: Shading,
+    scale: f64,
+    rotate: Rotation,
+    translate: Location,
+    drop_to_bed: bool,
+    smoothing: Option<Angle>,
+    layer_lines: Option<LayerLines>,
+}
+
+impl Mesh {
+    pub(crate) fn summary(&self) -> Result<MeshSummary, Failure> {
+        Ok(MeshSummary {
+            source: self.source.content_hash()?,
+            material: self.material,
+            shading: self.shading.clone(),
+            scale: self.scale,
+            rotate: self.rotate,
+            translate: self.translate,
+            drop_to_bed: self.drop_to_bed,
+            smoothing: self.smoothing,
+            layer_lines: self.layer_lines,
+        })
+    }
+}
+
+impl Mesh {
+    pub(crate) fn metadata(&self) -> Result<MeshMetadata, Failure> {
+        Ok(MeshMetadata {
+            path: self
+                .source
+                .path()
+                .map(|path| path.to_string_lossy().into_owned()),
+            hash: self.source.content_hash()?.to_string(),
+            material: self.material,
+        })
+    }
 }
 
 impl Into<Mesh> for MeshSource {
@@ -81,25 +383,78 @@ impl Into<Mesh> for MeshSource {
     }
 }
 
-impl Mesh {}
-
 impl SceneAdd<Mesh> for Scene {
     fn add(&mut self, node: Mesh) {
-        let object: rpt::Object = node.into();
-
-        self.add(object);
+        for object in node.into_objects(&[]).unwrap().0 {
+            self.add(object);
+        }
     }
 }
 
-impl Into<rpt::Object> for Mesh {
-    fn into(self) -> rpt::Object {
-        let material = self.material;
-        let mesh = self.source.load().unwrap();
-        let mesh = mesh.scale(&scale_all(self.scale));
-        let mesh = self.rotate.rotate_rpt(mesh);
-        let mesh = mesh.translate(&self.translate.to_offset());
+impl Mesh {
+    // The objects to add to the scene, and how many triangles they hold.
+    pub(crate) fn into_objects(
+        self,
+        clipping: &[ClippingPlane],
+    ) -> Result<(Vec<rpt::Object>, usize), Failure> {
+        match self.shading {
+            Shading::Material if clipping.is_empty() && self.layer_lines.is_none() => {
+                let triangles = self.surface(self.source.triangles()?);
+                let count = triangles.len();
+                let matrix = self.matrix(&triangles);
+                let material = self.material;
+                let mesh = rpt::Mesh::new(triangles).transform(matrix);
+
+                Ok((
+                    vec![rpt::Object::new(mesh).material(material.into())],
+                    count,
+                ))
+            }
+            Shading::Material => {
+                let triangles = self.surface(self.triangles()?);
+                let clipped = clip_all(clipping, &triangles);
+                let count = clipped.len();
+                let mut objects = vec![];
+
+                if !clipped.is_empty() {
+                    objects.push(
+                        rpt::Object::new(rpt::Mesh::new(clipped)).material(self.material.into()),
+                    );
+                }
+                objects.extend(cap_objects(clipping, &triangles));
+
+                Ok((objects, count))
+            }
+            Shading::Overhang(settings) => {
+                let triangles = self.surface(self.triangles()?);
+                let mut objects =
+                    OverhangReport::of(&triangles, &settings).heat_map(&triangles, clipping);
+                objects.extend(cap_objects(clipping, &triangles));
+
+                Ok((objects, triangles.len()))
+            }
+            Shading::Deviation(ref shading) => {
+                let triangles = self.surface(self.triangles()?);
+                let mut objects = shading.heat_map(&triangles, clipping);
+                objects.extend(cap_objects(clipping, &triangles));
 
-        rpt::Object::new(mesh).material(material.into())
+                Ok((objects, triangles.len()))
+            }
+        }
+    }
+
+    // Layer lines follow the build direction, so they need the triangles
+    // already transformed.
+    fn surface(&self, triangles: Vec<Triangle>) -> Vec<Triangle> {
+        let triangles = match self.smoothing {
+            Some(crease) => smooth_normals(&triangles, crease),
+            None => triangles,
+        };
+
+        match self.layer_lines {
+            Some(layer_lines) => layer_lines.apply(&triangles),
+            None => triangles,
+        }
     }
 }
 
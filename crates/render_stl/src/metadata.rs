@@ -0,0 +1,226 @@
+//! Provenance for rendered images, so an old thumbnail can be traced back to
+//! the mesh and settings that produced it.
+//!
+//! In PNGs, both rendered images and 16-bit passes, the metadata goes in a
+//! `tEXt` chunk for `Software` and UTF-8 `iTXt` chunks for `Source` and
+//! `render_stl`, which holds all of `RenderMetadata` as JSON. EXR passes
+//! hold the same JSON in a `render_stl` string attribute.
+
+use std::{convert::TryFrom, io::Read};
+
+use project::Failure;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    camera::Camera, clipping::ClippingPlane, color::Color, light_source::LightSource,
+    material::Material, model::RenderSettings, postprocess::PostProcessStep,
+};
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+const EXR_MAGIC: [u8; 4] = [0x76, 0x2f, 0x31, 0x01];
+pub(crate) const METADATA_KEYWORD: &str = "render_stl";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenderMetadata {
+    /// The `render_stl` version that rendered the image.
+    pub version: String,
+    pub meshes: Vec<MeshMetadata>,
+    pub camera: Camera,
+    pub lights: Vec<LightSource>,
+    pub background: Option<Color>,
+    pub settings: RenderSettings,
+    #[serde(default)]
+    pub clipping: Vec<ClippingPlane>,
+    #[serde(default)]
+    pub post_processing: Vec<PostProcessStep>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MeshMetadata {
+    /// `None` for meshes that didn't come from a file.
+    pub path: Option<String>,
+    /// The SHA-256 of the mesh, as in `MeshSource::content_hash`.
+    pub hash: String,
+    pub material: Material,
+}
+
+impl RenderMetadata {
+    pub const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+
+    /// The metadata from a PNG written by `Model::render` or
+    /// `Passes::write_png`, or `None` if the image doesn't have any.
+    pub fn from_png(source: impl Read) -> Result<Option<RenderMetadata>, Failure> {
+        let text = read_png_text(source)?;
+
+        match text.iter().find(|(keyword, _)| keyword == METADATA_KEYWORD) {
+            Some((_, json)) => Ok(Some(serde_json::from_str(json)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// The metadata from an EXR written by `Passes::write_exr`, or `None`
+    /// if the file doesn't have any.
+    pub fn from_exr(mut source: impl Read) -> Result<Option<RenderMetadata>, Failure> {
+        let mut exr = vec![];
+        source.read_to_end(&mut exr)?;
+
+        if !exr.starts_with(&EXR_MAGIC) {
+            return Err("Not an OpenEXR file".into());
+        }
+
+        // The header is a list of attributes after the magic number and
+        // version, ended by an empty name.
+        let mut rest = exr.get(8..).ok_or("Truncated OpenEXR header")?;
+        loop {
+            let (name, after) = split_null(rest).ok_or("Truncated OpenEXR header")?;
+            if name.is_empty() {
+                return Ok(None);
+            }
+
+            let (kind, after) = split_null(after).ok_or("Truncated OpenEXR header")?;
+            let size = after.get(..4).ok_or("Truncated OpenEXR header")?;
+            let size = i32::from_le_bytes([size[0], size[1], size[2], size[3]]);
+            let value = usize::try_from(size)
+                .ok()
+                .and_then(|size| after.get(4..4 + size))
+                .ok_or("Truncated OpenEXR attribute")?;
+
+            if name == METADATA_KEYWORD.as_bytes() && kind == b"string" {
+                return Ok(Some(serde_json::from_slice(value)?));
+            }
+
+            rest = &after[4 + value.len()..];
+        }
+    }
+
+    /// Adds the metadata to an encoded PNG.
+    pub fn write_to_png(&self, png: &[u8]) -> Result<Vec<u8>, Failure> {
+        let sources: Vec<_> = self
+            .meshes
+            .iter()
+            .filter_map(|mesh| mesh.path.as_deref())
+            .collect();
+
+        let mut chunks = vec![text_chunk(
+            "Software",
+            &format!("render_stl {}", self.version),
+        )];
+        if !sources.is_empty() {
+            chunks.push(international_text_chunk("Source", &sources.join("\n")));
+        }
+        chunks.push(international_text_chunk(
+            METADATA_KEYWORD,
+            &serde_json::to_string(self)?,
+        ));
+
+        // Text chunks can go anywhere after the header, so put them just
+        // before the IEND chunk, which is always the last 12 bytes.
+        let end = png
+            .len()
+            .checked_sub(12)
+            .filter(|end| png.starts_with(&SIGNATURE) && &png[end + 4..end + 8] == b"IEND")
+            .ok_or("Not a complete PNG")?;
+
+        let mut output = png[..end].to_vec();
+        for chunk in chunks {
+            output.extend(chunk);
+        }
+        output.extend(&png[end..]);
+
+        Ok(output)
+    }
+}
+
+/// Every `tEXt` and uncompressed `iTXt` entry in a PNG, as
+/// `(keyword, text)`.
+pub fn read_png_text(mut source: impl Read) -> Result<Vec<(String, String)>, Failure> {
+    let mut png = vec![];
+    source.read_to_end(&mut png)?;
+
+    if !png.starts_with(&SIGNATURE) {
+        return Err("Not a PNG".into());
+    }
+
+    let mut text = vec![];
+    let mut rest = &png[SIGNATURE.len()..];
+
+    while rest.len() >= 12 {
+        let length = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+        let kind = &rest[4..8];
+        let data = rest.get(8..8 + length).ok_or("Truncated PNG chunk")?;
+
+        match kind {
+            b"tEXt" => {
+                if let Some((keyword, value)) = split_null(data) {
+                    // tEXt is Latin-1, which maps directly onto the first
+                    // 256 code points.
+                    text.push((latin1(keyword), latin1(value)));
+                }
+            }
+            b"iTXt" => {
+                if let Some(entry) = parse_international_text(data) {
+                    text.push(entry);
+                }
+            }
+            b"IEND" => break,
+            _ => {}
+        }
+
+        rest = rest.get(12 + length..).unwrap_or_default();
+    }
+
+    Ok(text)
+}
+
+fn chunk(kind: &[u8; 4], data: &[u8]) -> Vec<u8> {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(kind);
+    hasher.update(data);
+
+    let mut chunk = Vec::with_capacity(data.len() + 12);
+    chunk.extend(&(data.len() as u32).to_be_bytes());
+    chunk.extend(kind);
+    chunk.extend(data);
+    chunk.extend(&hasher.finalize().to_be_bytes());
+    chunk
+}
+
+// Only for ASCII text, which is also valid Latin-1.
+fn text_chunk(keyword: &str, text: &str) -> Vec<u8> {
+    chunk(
+        b"tEXt",
+        &[keyword.as_bytes(), &[0], text.as_bytes()].concat(),
+    )
+}
+
+// Uncompressed, with no language tag or translated keyword.
+fn international_text_chunk(keyword: &str, text: &str) -> Vec<u8> {
+    chunk(
+        b"iTXt",
+        &[keyword.as_bytes(), &[0, 0, 0, 0, 0], text.as_bytes()].concat(),
+    )
+}
+
+fn parse_international_text(data: &[u8]) -> Option<(String, String)> {
+    let (keyword, rest) = split_null(data)?;
+    let (&compressed, rest) = rest.split_first()?;
+    let (_method, rest) = rest.split_first()?;
+    let (_language, rest) = split_null(rest)?;
+    let (_translated, text) = split_null(rest)?;
+
+    if compressed != 0 {
+        return None;
+    }
+
+    Some((latin1(keyword), String::from_utf8(text.to_vec()).ok()?))
+}
+
+fn split_null(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    let null = data.iter().position(|byte| *byte == 0)?;
+
+    Some((&data[..null], &data[null + 1..]))
+}
+
+fn latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| *byte as char).collect()
+}
@@ -1,35 +1,231 @@
 use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, Instant};
 
-use project::{Nothing, Outcome};
-use rpt::image::{DynamicImage, ImageOutputFormat, RgbImage};
-use rpt::{Scene, SceneAdd};
+use project::{Failure, Nothing, Outcome};
+use rpt::glm::DVec3;
+use rpt::image::{DynamicImage, ImageOutputFormat};
+use rpt::{Environment, Scene, SceneAdd};
+use serde::{Deserialize, Serialize};
 
+use crate::adaptive::{self, AdaptiveSampling};
+use crate::analysis::MeshAnalysis;
+use crate::annotate::Annotations;
+use crate::cache::Fingerprint;
 use crate::camera::Camera;
+use crate::clipping::{clip_all, ClippingPlane};
+use crate::color::Color;
+use crate::denoise::Denoise;
+use crate::direction::Direction;
 use crate::light_source::LightSource;
 use crate::location::Location;
 use crate::material::Material;
-use crate::mesh::Mesh;
+use crate::mesh::{Mesh, MeshSummary, Shading};
+use crate::metadata::RenderMetadata;
+use crate::passes::{self, Passes};
+use crate::postprocess::{self as postprocess, PostProcess, PostProcessStep};
 use crate::rotation::Rotation;
+use crate::scene::SceneDescription;
+use crate::view;
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RenderSettings {
+    pub width: u32,
+    pub height: u32,
+    /// Samples per pixel. With adaptive sampling, the fewest to take.
+    pub samples: u32,
+    pub max_bounces: u32,
+    pub exposure: f64,
+    pub adaptive: Option<AdaptiveSampling>,
+    pub denoise: Option<Denoise>,
+    /// Gives the image an alpha channel, clear wherever the background
+    /// shows. The background still lights the scene.
+    pub transparent: bool,
+}
+
+impl RenderSettings {
+    pub const DEFAULT: RenderSettings = RenderSettings {
+        width: 400,
+        height: 300,
+        samples: 5,
+        max_bounces: 4,
+        exposure: 2.5,
+        adaptive: None,
+        denoise: None,
+        transparent: false,
+    };
+
+    pub fn adaptive(self, adaptive: AdaptiveSampling) -> RenderSettings {
+        RenderSettings {
+            adaptive: Some(adaptive),
+            ..self
+        }
+    }
+
+    pub fn denoise(self, denoise: Denoise) -> RenderSettings {
+        RenderSettings {
+            denoise: Some(denoise),
+            ..self
+        }
+    }
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// What `Model::render_image` produced, and how long it took.
+#[derive(Debug, Copy, Clone)]
+pub struct RenderStats {
+    /// The size rendered, before any post-processing.
+    pub width: u32,
+    pub height: u32,
+    /// Samples per pixel actually taken.
+    pub samples: u32,
+    pub triangles: usize,
+    /// Loading meshes and building the scene.
+    pub setup: Duration,
+    /// Path tracing, tracing any auxiliary passes and denoising.
+    pub render: Duration,
+    pub post_processing: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderedImage {
+    /// RGBA if the settings ask for a transparent background, and RGB
+    /// otherwise.
+    pub image: DynamicImage,
+    pub stats: RenderStats,
+    /// Only rendered when asked for with `Model::auxiliary_passes`.
+    pub passes: Option<Passes>,
+}
 
 pub struct Model {
-    scene: Scene,
+    background: Option<Color>,
+    lights: Vec<LightSource>,
     camera: Camera,
+    settings: RenderSettings,
     mesh: Mesh,
+    others: Vec<Mesh>,
+    post_processing: Vec<PostProcess>,
+    auxiliary_passes: bool,
+    clipping: Vec<ClippingPlane>,
+    annotations: Option<Annotations>,
 }
 
 impl Model {
     pub fn new(mesh: impl Into<Mesh>) -> Model {
         Model {
-            scene: Scene::new(),
+            background: None,
+            lights: vec![],
             camera: Camera::default(),
+            settings: RenderSettings::DEFAULT,
             mesh: mesh.into(),
+            others: vec![],
+            post_processing: vec![],
+            auxiliary_passes: false,
+            clipping: vec![],
+            annotations: None,
         }
     }
 
-    pub fn add_light(mut self, light_source: impl Into<LightSource>) -> Self {
-        let light_source = light_source.into();
+    /// Adds another mesh to the scene. The transform methods on `Model` only
+    /// apply to the mesh it was created with, so configure this one first.
+    pub fn add_mesh(mut self, mesh: impl Into<Mesh>) -> Self {
+        self.others.push(mesh.into());
+        self
+    }
+
+    pub fn camera(mut self, camera: Camera) -> Self {
+        self.camera = camera;
+        self
+    }
 
-        self.scene.add(light_source);
+    pub fn background(mut self, color: impl Into<Color>) -> Self {
+        self.background = Some(color.into());
+        self
+    }
+
+    pub fn settings(mut self, settings: RenderSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Loads a model from a TOML scene description. See `scene` for the
+    /// format.
+    pub fn from_toml(path: impl AsRef<Path>) -> Result<Model, Failure> {
+        let path = path.as_ref();
+        let root = path.parent().unwrap_or_else(|| Path::new("."));
+
+        SceneDescription::load(path)?.into_model(root)
+    }
+
+    /// Also renders depth, normal and object ID passes. See `passes`.
+    pub fn auxiliary_passes(mut self) -> Self {
+        self.auxiliary_passes = true;
+        self
+    }
+
+    /// Cuts every mesh open along `plane`. See `clipping`.
+    pub fn clip(mut self, plane: ClippingPlane) -> Self {
+        self.clipping.push(plane);
+        self
+    }
+
+    /// Cuts the model at `height` along `axis` and looks straight down at
+    /// the cut, framing what's left.
+    pub fn cross_section(self, axis: impl Into<Direction>, height: f64) -> Result<Self, Failure> {
+        let plane = ClippingPlane::at(axis, height);
+        let model = self.clip(plane);
+        let triangles = model.visible_triangles()?;
+
+        if triangles.is_empty() {
+            return Err(format!("Nothing is left below {} along the axis", height).into());
+        }
+
+        let direction: DVec3 = plane.normal.into();
+        let up = if direction.z.abs() > 0.9 {
+            DVec3::new(0.0, 1.0, 0.0)
+        } else {
+            DVec3::new(0.0, 0.0, 1.0)
+        };
+        let camera = view::frame(
+            -direction,
+            up,
+            &MeshAnalysis::of(&triangles),
+            &model.settings,
+        );
+
+        Ok(model.camera(camera))
+    }
+
+    /// Draws dimension lines over the render. See `annotate`.
+    pub fn annotate(mut self, annotations: Annotations) -> Self {
+        self.annotations = Some(annotations);
+        self
+    }
+
+    // Every mesh, transformed and clipped.
+    fn visible_triangles(&self) -> Result<Vec<rpt::Triangle>, Failure> {
+        let mut triangles = vec![];
+        for mesh in std::iter::once(&self.mesh).chain(&self.others) {
+            triangles.extend(clip_all(&self.clipping, &mesh.triangles()?));
+        }
+
+        Ok(triangles)
+    }
+
+    /// Adds a step to run on the rendered image, after any added before.
+    pub fn post_process(mut self, step: PostProcess) -> Self {
+        self.post_processing.push(step);
+        self
+    }
+
+    pub fn add_light(mut self, light_source: impl Into<LightSource>) -> Self {
+        self.lights.push(light_source.into());
         self
     }
 
@@ -53,39 +249,221 @@ impl Model {
         self
     }
 
-    pub fn render(self, target: &mut impl Write) -> Outcome {
+    pub fn shading(mut self, shading: Shading) -> Self {
+        self.mesh = self.mesh.shading(shading);
+        self
+    }
+
+    /// A hash of the meshes and everything else that affects the rendered
+    /// image. See `cache::RenderCache`. `None` if there's a custom
+    /// post-processing step, since there's no telling what it does.
+    pub fn fingerprint(&self) -> Result<Option<Fingerprint>, Failure> {
+        let post_processing = self.post_processing_steps();
+        if post_processing
+            .iter()
+            .any(|step| matches!(step, PostProcessStep::Custom))
+        {
+            return Ok(None);
+        }
+
+        let description = RenderDescription {
+            meshes: std::iter::once(&self.mesh)
+                .chain(&self.others)
+                .map(Mesh::summary)
+                .collect::<Result<_, _>>()?,
+            camera: &self.camera,
+            lights: &self.lights,
+            background: self.background,
+            settings: &self.settings,
+            clipping: &self.clipping,
+            annotations: &self.annotations,
+            post_processing,
+        };
+
+        Ok(Some(Fingerprint::of(serde_json::to_vec(&description)?)))
+    }
+
+    fn post_processing_steps(&self) -> Vec<PostProcessStep> {
+        self.post_processing
+            .iter()
+            .map(PostProcess::describe)
+            .collect()
+    }
+
+    /// What `render` records in the image about how it was made.
+    pub fn metadata(&self) -> Result<RenderMetadata, Failure> {
+        Ok(RenderMetadata {
+            version: RenderMetadata::VERSION.to_string(),
+            meshes: std::iter::once(&self.mesh)
+                .chain(&self.others)
+                .map(Mesh::metadata)
+                .collect::<Result<_, _>>()?,
+            camera: self.camera,
+            lights: self.lights.clone(),
+            background: self.background,
+            settings: self.settings,
+            clipping: self.clipping.clone(),
+            post_processing: self.post_processing_steps(),
+        })
+    }
+
+    /// Renders the scene and runs the post-processing steps, without
+    /// encoding the result.
+    pub fn render_image(self) -> Result<RenderedImage, Failure> {
+        let bounds = match &self.annotations {
+            Some(annotations) if annotations.bounding_box => {
+                Some(MeshAnalysis::of(&self.visible_triangles()?))
+            }
+            _ => None,
+        };
+        let metadata = if self.auxiliary_passes {
+            Some(self.metadata()?)
+        } else {
+            None
+        };
+
         let Self {
-            mut scene,
+            background,
+            lights,
             camera,
+            settings,
             mesh,
+            others,
+            post_processing,
+            auxiliary_passes,
+            clipping,
+            annotations,
         } = self;
 
-        scene.add(mesh);
+        let start = Instant::now();
+        let mut scene = Scene::new();
+
+        if let Some(background) = background {
+            scene.environment = Environment::Color(background.into());
+        }
+
+        for light in lights {
+            scene.add(light);
+        }
+
+        let mut triangles = 0;
+        let mut object_ids = vec![];
+        for (id, mesh) in (1..).zip(std::iter::once(mesh).chain(others)) {
+            let (objects, count) = mesh.into_objects(&clipping)?;
+            triangles += count;
+
+            for object in objects {
+                scene.add(object);
+                object_ids.push(id);
+            }
+        }
+
+        let setup = start.elapsed();
+        let start = Instant::now();
+
+        let projection = camera;
+        let camera: rpt::Camera = camera.into();
+        let render_samples = |samples| {
+            rpt::Renderer::new(&scene, camera)
+                .max_bounces(settings.max_bounces)
+                .num_samples(samples)
+                .exposure_value(settings.exposure)
+                .width(settings.width)
+                .height(settings.height)
+                .render()
+        };
+
+        let (mut image, samples) = match &settings.adaptive {
+            Some(adaptive) => adaptive::render(
+                adaptive,
+                settings.samples,
+                (settings.width, settings.height),
+                render_samples,
+            ),
+            None => (render_samples(settings.samples), settings.samples),
+        };
+
+        // The denoiser is guided by the passes, even if they weren't asked
+        // for.
+        let passes = (auxiliary_passes || settings.denoise.is_some()).then(|| {
+            Passes::trace(
+                &scene,
+                &camera,
+                &object_ids,
+                settings.width,
+                settings.height,
+            )
+        });
+
+        if let (Some(denoise), Some(passes)) = (&settings.denoise, &passes) {
+            image = denoise.apply(&image, passes);
+        }
 
-        // scene.add(Light::Point(
-        //     glm::vec3(80.0, 80.0, 80.0),
-        //     glm::vec3(0.0, 5.0, 5.0),
-        // ));
+        let passes = passes
+            .filter(|_| auxiliary_passes)
+            .map(|passes| Passes { metadata, ..passes });
 
-        // scene.add(Light::Directional(
-        //     glm::vec3(2.0, 2.0, 2.0),
-        //     glm::vec3(1.0, -1.0, 0.0).normalize(),
-        // ));
+        let render = start.elapsed();
+        let start = Instant::now();
 
-        let image = rpt::Renderer::new(&scene, camera.into())
-            .max_bounces(4)
-            .num_samples(5)
-            .exposure_value(2.5)
-            .width(400)
-            .height(300)
-            .render();
+        let mut image = if settings.transparent {
+            DynamicImage::ImageRgba8(passes::with_alpha(image, &scene, &camera))
+        } else {
+            DynamicImage::ImageRgb8(image)
+        };
 
-        encode(image, target)
+        if let Some(annotations) = &annotations {
+            image = postprocess::in_rgba(image, |mut image| {
+                annotations.draw(&mut image, &projection, bounds.as_ref());
+                image
+            });
+        }
+
+        for step in &post_processing {
+            image = step.apply(image);
+        }
+
+        Ok(RenderedImage {
+            image,
+            stats: RenderStats {
+                width: settings.width,
+                height: settings.height,
+                samples,
+                triangles,
+                setup,
+                render,
+                post_processing: start.elapsed(),
+            },
+            passes,
+        })
     }
+
+    /// Renders to a PNG, with `metadata` embedded in it.
+    pub fn render(self, target: &mut impl Write) -> Outcome {
+        let metadata = self.metadata()?;
+        let rendered = self.render_image()?;
+
+        let mut png = vec![];
+        encode(rendered.image, &mut png)?;
+        target.write_all(&metadata.write_to_png(&png)?)?;
+
+        Ok(Nothing)
+    }
+}
+
+#[derive(Serialize)]
+struct RenderDescription<'a> {
+    meshes: Vec<MeshSummary>,
+    camera: &'a Camera,
+    lights: &'a [LightSource],
+    background: Option<Color>,
+    settings: &'a RenderSettings,
+    clipping: &'a [ClippingPlane],
+    annotations: &'a Option<Annotations>,
+    post_processing: Vec<PostProcessStep>,
 }
 
-pub fn encode(image: RgbImage, target: &mut impl Write) -> Outcome {
-    let image = DynamicImage::ImageRgb8(image);
+pub fn encode(image: DynamicImage, target: &mut impl Write) -> Outcome {
     image.write_to(target, ImageOutputFormat::Png)?;
 
     Ok(Nothing)
@@ -0,0 +1,31 @@
+use std::io::Write;
+
+use project::{Nothing, Outcome};
+
+use crate::{geometry::index, part::Part};
+
+/// Writes parts as a Wavefront .OBJ, with each part in its own group.
+pub fn write_obj(parts: &[Part], target: &mut impl Write) -> Outcome {
+    writeln!(target, "# render_stl")?;
+
+    // OBJ indices are 1-based and global to the file.
+    let mut offset = 1;
+
+    for part in parts {
+        let (vertices, faces) = index(&part.triangles);
+
+        writeln!(target, "g {}", part.name.replace(char::is_whitespace, "_"))?;
+
+        for vertex in &vertices {
+            writeln!(target, "v {} {} {}", vertex.x, vertex.y, vertex.z)?;
+        }
+
+        for [a, b, c] in &faces {
+            writeln!(target, "f {} {} {}", a + offset, b + offset, c + offset)?;
+        }
+
+        offset += vertices.len();
+    }
+
+    Ok(Nothing)
+}
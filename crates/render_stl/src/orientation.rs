@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+
+use rpt::{glm::DVec3, Triangle};
+
+use crate::{
+    angle::Angle,
+    geometry::{area_vector, transform_triangle},
+    overhang::{OverhangReport, OverhangSettings},
+    rotation::Rotation,
+};
+
+// Only the largest flat regions are worth trying as the bed face.
+const FACE_CANDIDATES: usize = 32;
+const GRID_STEP_DEGREES: usize = 30;
+// How flat a face has to be, in degrees past vertical, to count as resting
+// on the bed rather than touching it along an edge.
+const FLAT_ON_BED: f64 = 89.9;
+
+#[derive(Debug, Copy, Clone)]
+pub struct Orientation {
+    pub rotation: Rotation,
+    pub bed_contact_area: f64,
+    pub unsupported_area: f64,
+}
+
+impl Orientation {
+    fn score(&self) -> f64 {
+        self.bed_contact_area - self.unsupported_area
+    }
+}
+
+/// Tries resting each of the mesh's largest flat regions on the bed, plus a
+/// coarse grid of rotations, and keeps the one with the most bed contact and
+/// the least unsupported overhang. The mesh is assumed to be printed along
+/// +Z.
+pub fn best_orientation(triangles: &[Triangle], settings: &OverhangSettings) -> Orientation {
+    candidates(triangles)
+        .into_iter()
+        .map(|rotation| evaluate(triangles, rotation, settings))
+        .fold(None, |best: Option<Orientation>, candidate| match best {
+            Some(best) if best.score() >= candidate.score() => Some(best),
+            _ => Some(candidate),
+        })
+        .unwrap_or(Orientation {
+            rotation: Rotation::ZERO,
+            bed_contact_area: 0.0,
+            unsupported_area: 0.0,
+        })
+}
+
+pub fn evaluate(
+    triangles: &[Triangle],
+    rotation: Rotation,
+    settings: &OverhangSettings,
+) -> Orientation {
+    let matrix = rotation.euler_matrix();
+    let rotated: Vec<Triangle> = triangles
+        .iter()
+        .map(|triangle| transform_triangle(&matrix, triangle))
+        .collect();
+
+    let report = OverhangReport::of(&rotated, settings);
+    let bed_contact_area = report
+        .faces
+        .iter()
+        .filter(|face| face.on_bed && face.angle.to_degrees() >= FLAT_ON_BED)
+        .map(|face| face.area)
+        .fold(0.0, |total, area| total + area);
+
+    Orientation {
+        rotation,
+        bed_contact_area,
+        unsupported_area: report.unsupported_area,
+    }
+}
+
+fn candidates(triangles: &[Triangle]) -> Vec<Rotation> {
+    let mut candidates = vec![Rotation::euler(Angle::ZERO, Angle::ZERO, Angle::ZERO)];
+
+    let axes = [
+        DVec3::new(1.0, 0.0, 0.0),
+        DVec3::new(-1.0, 0.0, 0.0),
+        DVec3::new(0.0, 1.0, 0.0),
+        DVec3::new(0.0, -1.0, 0.0),
+        DVec3::new(0.0, 0.0, 1.0),
+    ];
+    candidates.extend(axes.iter().map(face_down));
+    candidates.extend(largest_normals(triangles).iter().map(face_down));
+
+    for x in (0..360).step_by(GRID_STEP_DEGREES) {
+        for y in (0..=180).step_by(GRID_STEP_DEGREES) {
+            candidates.push(Rotation::euler(
+                Angle::degrees(x as f64),
+                Angle::degrees(y as f64),
+                Angle::ZERO,
+            ));
+        }
+    }
+
+    candidates
+}
+
+// The face normals with the most area behind them, with nearly parallel
+// normals grouped together.
+fn largest_normals(triangles: &[Triangle]) -> Vec<DVec3> {
+    let mut groups: HashMap<(i64, i64, i64), (DVec3, f64)> = HashMap::new();
+
+    for triangle in triangles {
+        let area_vector = area_vector(triangle);
+        let area = area_vector.norm();
+
+        if area == 0.0 {
+            continue;
+        }
+
+        let normal = area_vector / area;
+        let key = |n: f64| (n * 1000.0).round() as i64;
+        let group = groups
+            .entry((key(normal.x), key(normal.y), key(normal.z)))
+            .or_insert((normal, 0.0));
+        group.1 += area;
+    }
+
+    let mut groups: Vec<_> = groups.into_values().collect();
+    groups.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    groups
+        .into_iter()
+        .take(FACE_CANDIDATES)
+        .map(|(normal, _)| normal)
+        .collect()
+}
+
+// The Euler rotation (around X, then Y) that points `normal` straight down.
+fn face_down(normal: &DVec3) -> Rotation {
+    let x = normal.y.atan2(normal.z);
+    let rest = normal.y.hypot(normal.z);
+    let y = normal.x.atan2(-rest);
+
+    Rotation::euler(Angle::radians(x), Angle::radians(y), Angle::ZERO)
+}
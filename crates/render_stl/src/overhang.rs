@@ -0,0 +1,223 @@
+use rpt::{glm::DVec3, Triangle};
+use serde::Serialize;
+
+use crate::{
+    angle::Angle,
+    clipping::{clip_all, ClippingPlane},
+    color::Color,
+    direction::Direction,
+    geometry::area_vector,
+    material::Material,
+};
+
+// Faces this close to the lowest point of the mesh rest on the bed and
+// don't need support.
+const BED_TOLERANCE: f64 = 1e-4;
+
+#[derive(Copy, Clone, Serialize)]
+pub struct OverhangSettings {
+    build_direction: Direction,
+    threshold: Angle,
+}
+
+impl OverhangSettings {
+    /// `threshold` is the steepest overhang, measured from vertical, that
+    /// the printer can bridge without support.
+    pub fn new(threshold: Angle) -> OverhangSettings {
+        OverhangSettings {
+            build_direction: (0, 0, 1).into(),
+            threshold,
+        }
+    }
+
+    /// The direction layers are stacked in. Defaults to +Z. Converting a
+    /// zero vector panics, so check ones from outside with `Direction::new`.
+    pub fn build_direction(self, build_direction: impl Into<Direction>) -> OverhangSettings {
+        OverhangSettings {
+            build_direction: build_direction.into(),
+            ..self
+        }
+    }
+}
+
+impl Default for OverhangSettings {
+    fn default() -> Self {
+        OverhangSettings::new(Angle::degrees(45.0))
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Severity {
+    /// Within the threshold, facing up, or resting on the bed.
+    Supported,
+    Mild,
+    Moderate,
+    Severe,
+}
+
+impl Severity {
+    const ALL: [Severity; 4] = [
+        Severity::Supported,
+        Severity::Mild,
+        Severity::Moderate,
+        Severity::Severe,
+    ];
+
+    pub fn is_supported(self) -> bool {
+        self == Severity::Supported
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            Severity::Supported => Color::hex(0xcccccc),
+            Severity::Mild => Color::hex(0xffd000),
+            Severity::Moderate => Color::hex(0xff7000),
+            Severity::Severe => Color::hex(0xff0000),
+        }
+    }
+
+    // Overhangs beyond the threshold are split into thirds between the
+    // threshold and a flat ceiling.
+    fn classify(angle: Angle, threshold: Angle) -> Severity {
+        let (angle, threshold) = (angle.to_degrees(), threshold.to_degrees());
+
+        if angle <= threshold {
+            return Severity::Supported;
+        }
+
+        let steepness = (angle - threshold) / (90.0 - threshold).max(f64::EPSILON);
+
+        if steepness < 1.0 / 3.0 {
+            Severity::Mild
+        } else if steepness < 2.0 / 3.0 {
+            Severity::Moderate
+        } else {
+            Severity::Severe
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct FaceOverhang {
+    pub triangle: usize,
+    /// How far the face leans past vertical. A vertical wall is 0° and a
+    /// flat ceiling is 90°.
+    pub angle: Angle,
+    pub area: f64,
+    pub on_bed: bool,
+    pub severity: Severity,
+}
+
+#[derive(Debug, Clone)]
+pub struct OverhangReport {
+    pub faces: Vec<FaceOverhang>,
+    pub total_area: f64,
+    pub unsupported_area: f64,
+    /// The steepest overhang that isn't resting on the bed.
+    pub worst: Angle,
+}
+
+impl OverhangReport {
+    pub fn of(triangles: &[Triangle], settings: &OverhangSettings) -> OverhangReport {
+        let up: DVec3 = settings.build_direction.into();
+
+        let bed = triangles
+            .iter()
+            .flat_map(|triangle| [triangle.v1, triangle.v2, triangle.v3])
+            .map(|vertex| vertex.dot(&up))
+            .fold(f64::INFINITY, f64::min);
+
+        let faces: Vec<FaceOverhang> = triangles
+            .iter()
+            .enumerate()
+            .map(|(index, triangle)| {
+                let area_vector = area_vector(triangle);
+                let area = area_vector.norm();
+
+                // Anything facing up or sideways leans 0° past vertical.
+                let downward = if area > 0.0 {
+                    -(area_vector / area).dot(&up)
+                } else {
+                    0.0
+                };
+                let angle = Angle::radians(downward.clamp(0.0, 1.0).asin());
+
+                let on_bed = [triangle.v1, triangle.v2, triangle.v3]
+                    .iter()
+                    .all(|vertex| vertex.dot(&up) - bed < BED_TOLERANCE);
+
+                let severity = if on_bed {
+                    Severity::Supported
+                } else {
+                    Severity::classify(angle, settings.threshold)
+                };
+
+                FaceOverhang {
+                    triangle: index,
+                    angle,
+                    area,
+                    on_bed,
+                    severity,
+                }
+            })
+            .collect();
+
+        let total_area = faces.iter().map(|face| face.area).sum();
+        let unsupported_area = faces
+            .iter()
+            .filter(|face| !face.severity.is_supported())
+            .map(|face| face.area)
+            .fold(0.0, |total, area| total + area);
+        let worst = faces
+            .iter()
+            .filter(|face| !face.on_bed)
+            .map(|face| face.angle)
+            .fold(Angle::ZERO, |worst, angle| {
+                if angle.to_degrees() > worst.to_degrees() {
+                    angle
+                } else {
+                    worst
+                }
+            });
+
+        OverhangReport {
+            faces,
+            total_area,
+            unsupported_area,
+            worst,
+        }
+    }
+
+    pub fn is_supported(&self) -> bool {
+        self.unsupported_area == 0.0
+    }
+
+    // One mesh per severity, so each can be given its own colour. Faces are
+    // classified before clipping, so a cut doesn't change their severity.
+    pub(crate) fn heat_map(
+        &self,
+        triangles: &[Triangle],
+        clipping: &[ClippingPlane],
+    ) -> Vec<rpt::Object> {
+        Severity::ALL
+            .iter()
+            .filter_map(|severity| {
+                let group: Vec<Triangle> = self
+                    .faces
+                    .iter()
+                    .filter(|face| face.severity == *severity)
+                    .map(|face| triangles[face.triangle])
+                    .collect();
+                let group = clip_all(clipping, &group);
+
+                if group.is_empty() {
+                    return None;
+                }
+
+                let material = Material::specular(severity.color(), 0.5);
+
+                Some(rpt::Object::new(rpt::Mesh::new(group)).material(material.into()))
+            })
+            .collect()
+    }
+}
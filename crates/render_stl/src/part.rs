@@ -0,0 +1,29 @@
+use rpt::Triangle;
+
+use crate::color::Color;
+
+/// A named, coloured group of triangles, for formats that can hold more than
+/// one object.
+#[derive(Clone)]
+pub struct Part {
+    pub name: String,
+    pub color: Color,
+    pub triangles: Vec<Triangle>,
+}
+
+impl Part {
+    pub fn new(name: impl Into<String>, triangles: Vec<Triangle>) -> Part {
+        Part {
+            name: name.into(),
+            color: Color::WHITE,
+            triangles,
+        }
+    }
+
+    pub fn color(self, color: impl Into<Color>) -> Part {
+        Part {
+            color: color.into(),
+            ..self
+        }
+    }
+}
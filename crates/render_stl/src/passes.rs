@@ -0,0 +1,402 @@
+//! Per-pixel buffers rendered alongside the image, for compositing and
+//! automated checks.
+
+use std::io::Write;
+
+use project::{Nothing, Outcome};
+use rpt::{
+    glm::DVec3,
+    image::{RgbImage, Rgba, RgbaImage},
+    HitRecord, Ray, Scene, Shape,
+};
+
+use crate::metadata::{RenderMetadata, METADATA_KEYWORD};
+
+const EPSILON: f64 = 1e-9;
+// Rays per side of a pixel on an edge, for its coverage.
+const COVERAGE_SAMPLES: u32 = 4;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Pass {
+    /// Distance from the camera along its view direction.
+    Depth,
+    /// World-space surface normals.
+    Normal,
+    /// The colour of the material at each pixel, without lighting.
+    Albedo,
+    /// Which mesh each pixel shows: 1 for the mesh the `Model` was created
+    /// with, then 2, 3… for meshes added after it. 0 is background.
+    ObjectId,
+}
+
+/// The auxiliary passes for a render, at the rendered size (before any
+/// post-processing). Pixels are stored row by row from the top left.
+#[derive(Debug, Clone)]
+pub struct Passes {
+    pub width: u32,
+    pub height: u32,
+    /// Infinite where a pixel shows background.
+    pub depth: Vec<f32>,
+    /// Zero where a pixel shows background.
+    pub normal: Vec<[f32; 3]>,
+    /// Linear material colour, zero where a pixel shows background.
+    pub albedo: Vec<[f32; 3]>,
+    pub object_id: Vec<u32>,
+    /// Embedded in every file the passes are written to, like the image.
+    pub metadata: Option<RenderMetadata>,
+}
+
+impl Passes {
+    /// Traces one ray through the centre of each pixel. `object_ids` gives
+    /// the ID for each of the scene's objects.
+    pub(crate) fn trace(
+        scene: &Scene,
+        camera: &rpt::Camera,
+        object_ids: &[u32],
+        width: u32,
+        height: u32,
+    ) -> Passes {
+        let size = (width * height) as usize;
+        let mut passes = Passes {
+            width,
+            height,
+            depth: vec![f32::INFINITY; size],
+            normal: vec![[0.0; 3]; size],
+            albedo: vec![[0.0; 3]; size],
+            object_id: vec![0; size],
+            metadata: None,
+        };
+
+        for y in 0..height {
+            for x in 0..width {
+                let ray = primary_ray(camera, width, height, (x as f64 + 0.5, y as f64 + 0.5));
+
+                let mut record = HitRecord::new();
+                let mut hit = None;
+
+                for (index, object) in scene.objects.iter().enumerate() {
+                    if object.shape.intersect(&ray, EPSILON, &mut record) {
+                        hit = Some(index);
+                    }
+                }
+
+                if let Some(index) = hit {
+                    let pixel = (y * width + x) as usize;
+                    let normal: DVec3 = record.normal.normalize();
+                    let albedo = scene.objects[index].material.color;
+
+                    passes.depth[pixel] = (record.time * ray.dir.dot(&camera.direction)) as f32;
+                    passes.normal[pixel] = [normal.x as f32, normal.y as f32, normal.z as f32];
+                    passes.albedo[pixel] = [albedo.x as f32, albedo.y as f32, albedo.z as f32];
+                    passes.object_id[pixel] = object_ids[index];
+                }
+            }
+        }
+
+        passes
+    }
+
+    /// The nearest and farthest depth of anything in view.
+    pub fn depth_range(&self) -> Option<(f32, f32)> {
+        self.depth
+            .iter()
+            .copied()
+            .filter(|depth| depth.is_finite())
+            .fold(None, |range, depth| match range {
+                None => Some((depth, depth)),
+                Some((near, far)) => Some((near.min(depth), far.max(depth))),
+            })
+    }
+
+    /// Writes one pass as a 16-bit PNG. Depth is scaled so the nearest
+    /// surface is black and the farthest is just below white, with white
+    /// for background. Normals map -1..1 to 0..65535 per channel, and
+    /// albedo maps 0..1. Object IDs are written as they are. Any metadata
+    /// is added as it is to rendered images.
+    pub fn write_png(&self, pass: Pass, target: &mut impl Write) -> Outcome {
+        let (color, samples): (_, Vec<u16>) = match pass {
+            Pass::Depth => {
+                let (near, far) = self.depth_range().unwrap_or((0.0, 1.0));
+                let range = (far - near).max(f32::EPSILON);
+                let samples = self
+                    .depth
+                    .iter()
+                    .map(|depth| {
+                        if depth.is_finite() {
+                            ((depth - near) / range * 65534.0).round() as u16
+                        } else {
+                            u16::MAX
+                        }
+                    })
+                    .collect();
+
+                (png::ColorType::Grayscale, samples)
+            }
+            Pass::Normal => {
+                let samples = self
+                    .normal
+                    .iter()
+                    .flat_map(|normal| {
+                        normal.map(|n| ((n.clamp(-1.0, 1.0) * 0.5 + 0.5) * 65535.0).round() as u16)
+                    })
+                    .collect();
+
+                (png::ColorType::RGB, samples)
+            }
+            Pass::Albedo => {
+                let samples = self
+                    .albedo
+                    .iter()
+                    .flat_map(|albedo| albedo.map(|a| (a.clamp(0.0, 1.0) * 65535.0).round() as u16))
+                    .collect();
+
+                (png::ColorType::RGB, samples)
+            }
+            Pass::ObjectId => {
+                let samples = self
+                    .object_id
+                    .iter()
+                    .map(|id| (*id).min(u16::MAX as u32) as u16)
+                    .collect();
+
+                (png::ColorType::Grayscale, samples)
+            }
+        };
+
+        // Written with png directly, since image doesn't write 16-bit
+        // samples in PNG's big-endian order.
+        let mut png = vec![];
+        let mut encoder = png::Encoder::new(&mut png, self.width, self.height);
+        encoder.set_color(color);
+        encoder.set_depth(png::BitDepth::Sixteen);
+
+        let bytes: Vec<u8> = samples
+            .iter()
+            .flat_map(|sample| sample.to_be_bytes())
+            .collect();
+        encoder.write_header()?.write_image_data(&bytes)?;
+
+        match &self.metadata {
+            Some(metadata) => target.write_all(&metadata.write_to_png(&png)?)?,
+            None => target.write_all(&png)?,
+        }
+
+        Ok(Nothing)
+    }
+
+    /// Writes every pass to one uncompressed OpenEXR file, with unscaled
+    /// values: depth as `Z`, normals as `N.X`, `N.Y` and `N.Z`, albedo as
+    /// `albedo.R`, `albedo.G` and `albedo.B`, and object IDs as the integer
+    /// channel `id`. Any metadata is stored as JSON in the `render_stl`
+    /// string attribute.
+    pub fn write_exr(&self, target: &mut impl Write) -> Outcome {
+        let channels = [
+            ExrChannel::Float("N.X", self.normal.iter().map(|n| n[0]).collect()),
+            ExrChannel::Float("N.Y", self.normal.iter().map(|n| n[1]).collect()),
+            ExrChannel::Float("N.Z", self.normal.iter().map(|n| n[2]).collect()),
+            ExrChannel::Float("Z", self.depth.clone()),
+            ExrChannel::Float("albedo.B", self.albedo.iter().map(|a| a[2]).collect()),
+            ExrChannel::Float("albedo.G", self.albedo.iter().map(|a| a[1]).collect()),
+            ExrChannel::Float("albedo.R", self.albedo.iter().map(|a| a[0]).collect()),
+            ExrChannel::Uint("id", self.object_id.clone()),
+        ];
+
+        let metadata = match &self.metadata {
+            Some(metadata) => Some(serde_json::to_string(metadata)?),
+            None => None,
+        };
+
+        target.write_all(&exr(
+            self.width,
+            self.height,
+            &channels,
+            metadata.as_deref(),
+        ))?;
+
+        Ok(Nothing)
+    }
+}
+
+// The same mapping from pixels to rays as rpt's renderer, where the field
+// of view spans the longer side. `pixel` is measured from the top left
+// corner of the image.
+fn primary_ray(camera: &rpt::Camera, width: u32, height: u32, pixel: (f64, f64)) -> Ray {
+    let dim = width.max(height) as f64;
+    let d = (camera.fov / 2.0).tan().recip();
+    let right = camera.direction.cross(&camera.up).normalize();
+
+    let xn = (2.0 * pixel.0 - width as f64) / dim;
+    let yn = (height as f64 - 2.0 * pixel.1) / dim;
+
+    Ray {
+        origin: camera.eye,
+        dir: (d * camera.direction + xn * right + yn * camera.up).normalize(),
+    }
+}
+
+/// Adds an alpha channel to a render of `scene`, from how much of each
+/// pixel the objects cover. Edge pixels are blended with the background, so
+/// that's taken back out of them.
+pub(crate) fn with_alpha(image: RgbImage, scene: &Scene, camera: &rpt::Camera) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    let hits = |pixel| {
+        let ray = primary_ray(camera, width, height, pixel);
+        let mut record = HitRecord::new();
+
+        scene
+            .objects
+            .iter()
+            .any(|object| object.shape.intersect(&ray, EPSILON, &mut record))
+    };
+
+    let centres: Vec<bool> = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| hits((x as f64 + 0.5, y as f64 + 0.5)))
+        .collect();
+    let centre = |x: i64, y: i64| {
+        let (x, y) = (x.clamp(0, width as i64 - 1), y.clamp(0, height as i64 - 1));
+        centres[(y * width as i64 + x) as usize]
+    };
+
+    // Only pixels next to an edge are sampled any further.
+    let mut coverage = vec![0.0; centres.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let (cx, cy) = (x as i64, y as i64);
+            let inside = centre(cx, cy);
+            let edge = (-1..=1)
+                .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+                .any(|(dx, dy)| centre(cx + dx, cy + dy) != inside);
+
+            coverage[(y * width + x) as usize] = if edge {
+                let step = 1.0 / COVERAGE_SAMPLES as f64;
+                let covered = (0..COVERAGE_SAMPLES * COVERAGE_SAMPLES)
+                    .filter(|sample| {
+                        let (sx, sy) = (sample % COVERAGE_SAMPLES, sample / COVERAGE_SAMPLES);
+                        hits((
+                            x as f64 + (sx as f64 + 0.5) * step,
+                            y as f64 + (sy as f64 + 0.5) * step,
+                        ))
+                    })
+                    .count();
+
+                covered as f64 / (COVERAGE_SAMPLES * COVERAGE_SAMPLES) as f64
+            } else if inside {
+                1.0
+            } else {
+                0.0
+            };
+        }
+    }
+
+    // Misses all show the same background.
+    let background = image
+        .pixels()
+        .zip(&coverage)
+        .find(|(_, coverage)| **coverage == 0.0)
+        .map(|(pixel, _)| *pixel);
+
+    let mut output = RgbaImage::new(width, height);
+    for ((pixel, alpha), output) in image.pixels().zip(&coverage).zip(output.pixels_mut()) {
+        let mut color = pixel.0;
+
+        if let (Some(background), true) = (background, *alpha > 0.0) {
+            for (channel, under) in color.iter_mut().zip(&background.0) {
+                let unblended = (*channel as f64 - *under as f64 * (1.0 - alpha)) / alpha;
+                *channel = unblended.round().clamp(0.0, 255.0) as u8;
+            }
+        }
+
+        let [r, g, b] = color;
+        *output = Rgba([r, g, b, (alpha * 255.0).round() as u8]);
+    }
+
+    output
+}
+
+enum ExrChannel {
+    Uint(&'static str, Vec<u32>),
+    Float(&'static str, Vec<f32>),
+}
+
+// A single-part scanline file without compression, one scanline per block.
+// Channels must already be sorted by name.
+fn exr(width: u32, height: u32, channels: &[ExrChannel], metadata: Option<&str>) -> Vec<u8> {
+    fn attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+        header.extend(name.as_bytes());
+        header.push(0);
+        header.extend(kind.as_bytes());
+        header.push(0);
+        header.extend(&(value.len() as i32).to_le_bytes());
+        header.extend(value);
+    }
+
+    let mut channel_list = vec![];
+    for channel in channels {
+        let (name, pixel_type) = match channel {
+            ExrChannel::Uint(name, _) => (name, 0i32),
+            ExrChannel::Float(name, _) => (name, 2i32),
+        };
+
+        channel_list.extend(name.as_bytes());
+        channel_list.push(0);
+        channel_list.extend(&pixel_type.to_le_bytes());
+        // pLinear and three reserved bytes, then x and y sampling.
+        channel_list.extend(&[0, 0, 0, 0]);
+        channel_list.extend(&1i32.to_le_bytes());
+        channel_list.extend(&1i32.to_le_bytes());
+    }
+    channel_list.push(0);
+
+    let window: Vec<u8> = [0, 0, width as i32 - 1, height as i32 - 1]
+        .iter()
+        .flat_map(|value: &i32| value.to_le_bytes())
+        .collect();
+
+    let mut file = vec![0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0];
+    attribute(&mut file, "channels", "chlist", &channel_list);
+    attribute(&mut file, "compression", "compression", &[0]);
+    attribute(&mut file, "dataWindow", "box2i", &window);
+    attribute(&mut file, "displayWindow", "box2i", &window);
+    attribute(&mut file, "lineOrder", "lineOrder", &[0]);
+    attribute(&mut file, "pixelAspectRatio", "float", &1f32.to_le_bytes());
+    if let Some(metadata) = metadata {
+        attribute(&mut file, METADATA_KEYWORD, "string", metadata.as_bytes());
+    }
+    attribute(
+        &mut file,
+        "screenWindowCenter",
+        "v2f",
+        &[0f32.to_le_bytes(), 0f32.to_le_bytes()].concat(),
+    );
+    attribute(&mut file, "screenWindowWidth", "float", &1f32.to_le_bytes());
+    file.push(0);
+
+    // Every channel is four bytes per pixel.
+    let line_size = channels.len() * width as usize * 4;
+    let table_end = file.len() + height as usize * 8;
+
+    for y in 0..height as usize {
+        let offset = table_end + y * (line_size + 8);
+        file.extend(&(offset as u64).to_le_bytes());
+    }
+
+    for y in 0..height as usize {
+        file.extend(&(y as i32).to_le_bytes());
+        file.extend(&(line_size as i32).to_le_bytes());
+
+        let row = y * width as usize..(y + 1) * width as usize;
+        for channel in channels {
+            match channel {
+                ExrChannel::Uint(_, values) => {
+                    file.extend(values[row.clone()].iter().flat_map(|v| v.to_le_bytes()))
+                }
+                ExrChannel::Float(_, values) => {
+                    file.extend(values[row.clone()].iter().flat_map(|v| v.to_le_bytes()))
+                }
+            }
+        }
+    }
+
+    file
+}
@@ -0,0 +1,82 @@
+use std::io::Write;
+
+use project::{Nothing, Outcome};
+
+use crate::{geometry::index, part::Part};
+
+pub enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+}
+
+/// Writes parts as a single .PLY, with each face coloured by its part.
+pub fn write_ply(parts: &[Part], target: &mut impl Write, format: PlyFormat) -> Outcome {
+    let indexed: Vec<_> = parts.iter().map(|part| index(&part.triangles)).collect();
+    let vertex_count: usize = indexed.iter().map(|(vertices, _)| vertices.len()).sum();
+    let face_count: usize = indexed.iter().map(|(_, faces)| faces.len()).sum();
+
+    let format_name = match format {
+        PlyFormat::Ascii => "ascii",
+        PlyFormat::BinaryLittleEndian => "binary_little_endian",
+    };
+
+    write!(
+        target,
+        "ply\n\
+         format {} 1.0\n\
+         comment render_stl\n\
+         element vertex {}\n\
+         property float x\n\
+         property float y\n\
+         property float z\n\
+         element face {}\n\
+         property list uchar int vertex_indices\n\
+         property uchar red\n\
+         property uchar green\n\
+         property uchar blue\n\
+         end_header\n",
+        format_name, vertex_count, face_count
+    )?;
+
+    for (vertices, _) in &indexed {
+        for vertex in vertices {
+            match format {
+                PlyFormat::Ascii => writeln!(target, "{} {} {}", vertex.x, vertex.y, vertex.z)?,
+                PlyFormat::BinaryLittleEndian => {
+                    for n in vertex.iter() {
+                        target.write_all(&(*n as f32).to_le_bytes())?;
+                    }
+                }
+            }
+        }
+    }
+
+    let mut offset = 0;
+
+    for (part, (vertices, faces)) in parts.iter().zip(&indexed) {
+        let (red, green, blue) = part.color.rgb();
+
+        for face in faces {
+            let [a, b, c] = face.map(|index| index + offset);
+
+            match format {
+                PlyFormat::Ascii => {
+                    writeln!(target, "3 {} {} {} {} {} {}", a, b, c, red, green, blue)?
+                }
+                PlyFormat::BinaryLittleEndian => {
+                    target.write_all(&[3])?;
+
+                    for index in [a, b, c] {
+                        target.write_all(&(index as i32).to_le_bytes())?;
+                    }
+
+                    target.write_all(&[red, green, blue])?;
+                }
+            }
+        }
+
+        offset += vertices.len();
+    }
+
+    Ok(Nothing)
+}
@@ -0,0 +1,343 @@
+use std::{fmt, sync::Arc};
+
+use rpt::image::{
+    imageops::{self, FilterType},
+    DynamicImage, Rgba, RgbaImage,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{cache::Fingerprint, color::Color};
+
+/// A step applied to the rendered image before it's encoded. Steps run in
+/// the order they're added to the `Model`, and keep a transparent image
+/// transparent.
+#[derive(Clone)]
+pub enum PostProcess {
+    /// Trims rows and columns that only contain background, judged by the
+    /// top left pixel. Channels, alpha included, within `tolerance` of it
+    /// count as background.
+    CropToContent { tolerance: u8 },
+    /// Adds a border. Without a colour, the top left pixel is used, so the
+    /// border matches the rendered background, even a transparent one.
+    Pad { pixels: u32, color: Option<Color> },
+    /// Scales the image to fit within `width` × `height`, keeping its
+    /// aspect ratio.
+    Fit { width: u32, height: u32 },
+    /// Blends `image` into a corner, `margin` pixels from the edges.
+    Watermark {
+        image: Arc<RgbaImage>,
+        corner: Corner,
+        margin: u32,
+        opacity: f64,
+    },
+    /// Gets an RGBA image if the background is transparent, and RGB
+    /// otherwise.
+    Custom(Arc<dyn Fn(DynamicImage) -> DynamicImage + Send + Sync>),
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// What a `PostProcess` step does, for fingerprints and metadata.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PostProcessStep {
+    CropToContent {
+        tolerance: u8,
+    },
+    Pad {
+        pixels: u32,
+        color: Option<Color>,
+    },
+    Fit {
+        width: u32,
+        height: u32,
+    },
+    Watermark {
+        /// The SHA-256 of the watermark's size and pixels.
+        hash: Fingerprint,
+        corner: Corner,
+        margin: u32,
+        opacity: f64,
+    },
+    /// A closure, which can't be described any further.
+    Custom,
+}
+
+impl PostProcess {
+    pub const fn crop_to_content() -> PostProcess {
+        PostProcess::CropToContent { tolerance: 2 }
+    }
+
+    pub const fn pad(pixels: u32) -> PostProcess {
+        PostProcess::Pad {
+            pixels,
+            color: None,
+        }
+    }
+
+    pub const fn fit(width: u32, height: u32) -> PostProcess {
+        PostProcess::Fit { width, height }
+    }
+
+    pub fn watermark(image: RgbaImage, corner: Corner) -> PostProcess {
+        PostProcess::Watermark {
+            image: Arc::new(image),
+            corner,
+            margin: 8,
+            opacity: 0.5,
+        }
+    }
+
+    pub fn custom(
+        step: impl Fn(DynamicImage) -> DynamicImage + Send + Sync + 'static,
+    ) -> PostProcess {
+        PostProcess::Custom(Arc::new(step))
+    }
+
+    pub fn apply(&self, image: DynamicImage) -> DynamicImage {
+        match self {
+            PostProcess::CropToContent { tolerance } => {
+                in_rgba(image, |image| crop_to_content(image, *tolerance))
+            }
+            PostProcess::Pad { pixels, color } => {
+                in_rgba(image, |image| pad(image, *pixels, *color))
+            }
+            PostProcess::Fit { width, height } => {
+                in_rgba(image, |image| fit(image, *width, *height))
+            }
+            PostProcess::Watermark {
+                image: mark,
+                corner,
+                margin,
+                opacity,
+            } => in_rgba(image, |image| {
+                watermark(image, mark, *corner, *margin, *opacity)
+            }),
+            PostProcess::Custom(step) => step(image),
+        }
+    }
+}
+
+impl PostProcess {
+    pub fn describe(&self) -> PostProcessStep {
+        match self {
+            PostProcess::CropToContent { tolerance } => PostProcessStep::CropToContent {
+                tolerance: *tolerance,
+            },
+            PostProcess::Pad { pixels, color } => PostProcessStep::Pad {
+                pixels: *pixels,
+                color: *color,
+            },
+            PostProcess::Fit { width, height } => PostProcessStep::Fit {
+                width: *width,
+                height: *height,
+            },
+            PostProcess::Watermark {
+                image,
+                corner,
+                margin,
+                opacity,
+            } => {
+                let (width, height) = image.dimensions();
+                let mut bytes = [width.to_le_bytes(), height.to_le_bytes()].concat();
+                bytes.extend_from_slice(image.as_raw());
+
+                PostProcessStep::Watermark {
+                    hash: Fingerprint::of(bytes),
+                    corner: *corner,
+                    margin: *margin,
+                    opacity: *opacity,
+                }
+            }
+            PostProcess::Custom(_) => PostProcessStep::Custom,
+        }
+    }
+}
+
+impl fmt::Debug for PostProcess {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PostProcess::CropToContent { tolerance } => f
+                .debug_struct("CropToContent")
+                .field("tolerance", tolerance)
+                .finish(),
+            PostProcess::Pad { pixels, color } => f
+                .debug_struct("Pad")
+                .field("pixels", pixels)
+                .field("color", color)
+                .finish(),
+            PostProcess::Fit { width, height } => f
+                .debug_struct("Fit")
+                .field("width", width)
+                .field("height", height)
+                .finish(),
+            PostProcess::Watermark {
+                image,
+                corner,
+                margin,
+                opacity,
+            } => f
+                .debug_struct("Watermark")
+                .field("size", &image.dimensions())
+                .field("corner", corner)
+                .field("margin", margin)
+                .field("opacity", opacity)
+                .finish(),
+            PostProcess::Custom(_) => f.write_str("Custom"),
+        }
+    }
+}
+
+/// Edits `image` as RGBA. An RGBA image stays that way, and anything else
+/// comes back as RGB.
+pub(crate) fn in_rgba(
+    image: DynamicImage,
+    edit: impl FnOnce(RgbaImage) -> RgbaImage,
+) -> DynamicImage {
+    match image {
+        DynamicImage::ImageRgba8(image) => DynamicImage::ImageRgba8(edit(image)),
+        image => {
+            DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(edit(image.into_rgba8())).into_rgb8())
+        }
+    }
+}
+
+fn background(image: &RgbaImage) -> Rgba<u8> {
+    if image.width() > 0 && image.height() > 0 {
+        *image.get_pixel(0, 0)
+    } else {
+        Rgba([0, 0, 0, 255])
+    }
+}
+
+fn crop_to_content(image: RgbaImage, tolerance: u8) -> RgbaImage {
+    let background = background(&image);
+    let is_content = |pixel: &Rgba<u8>| {
+        pixel
+            .0
+            .iter()
+            .zip(&background.0)
+            .any(|(channel, background)| channel.abs_diff(*background) > tolerance)
+    };
+
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (u32::MAX, u32::MAX, 0, 0);
+
+    for (x, y, pixel) in image.enumerate_pixels() {
+        if is_content(pixel) {
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+    }
+
+    // Nothing but background, so there's nothing to crop to.
+    if min_x > max_x {
+        return image;
+    }
+
+    imageops::crop_imm(&image, min_x, min_y, max_x - min_x + 1, max_y - min_y + 1).to_image()
+}
+
+fn pad(image: RgbaImage, pixels: u32, color: Option<Color>) -> RgbaImage {
+    let fill = match color {
+        Some(color) => {
+            let (r, g, b) = color.rgb();
+            Rgba([r, g, b, 255])
+        }
+        None => background(&image),
+    };
+
+    let mut padded = RgbaImage::from_pixel(
+        image.width() + pixels * 2,
+        image.height() + pixels * 2,
+        fill,
+    );
+    imageops::replace(&mut padded, &image, pixels, pixels);
+
+    padded
+}
+
+fn fit(image: RgbaImage, width: u32, height: u32) -> RgbaImage {
+    let scale = (width as f64 / image.width().max(1) as f64)
+        .min(height as f64 / image.height().max(1) as f64);
+    let width = ((image.width() as f64 * scale).round() as u32).max(1);
+    let height = ((image.height() as f64 * scale).round() as u32).max(1);
+
+    if image.pixels().all(|pixel| pixel.0[3] == 255) {
+        return imageops::resize(&image, width, height, FilterType::Lanczos3);
+    }
+
+    // Premultiplied, so the colour of transparent pixels doesn't bleed into
+    // the edges.
+    let mut image = image;
+    for pixel in image.pixels_mut() {
+        let alpha = pixel.0[3] as f64 / 255.0;
+        for channel in &mut pixel.0[..3] {
+            *channel = (*channel as f64 * alpha).round() as u8;
+        }
+    }
+
+    let mut image = imageops::resize(&image, width, height, FilterType::Lanczos3);
+    for pixel in image.pixels_mut() {
+        let alpha = pixel.0[3] as f64 / 255.0;
+        for channel in &mut pixel.0[..3] {
+            *channel = if alpha > 0.0 {
+                (*channel as f64 / alpha).round().min(255.0) as u8
+            } else {
+                0
+            };
+        }
+    }
+
+    image
+}
+
+fn watermark(
+    mut image: RgbaImage,
+    mark: &RgbaImage,
+    corner: Corner,
+    margin: u32,
+    opacity: f64,
+) -> RgbaImage {
+    let right = image.width().saturating_sub(mark.width() + margin);
+    let bottom = image.height().saturating_sub(mark.height() + margin);
+    let (left, top) = match corner {
+        Corner::TopLeft => (margin, margin),
+        Corner::TopRight => (right, margin),
+        Corner::BottomLeft => (margin, bottom),
+        Corner::BottomRight => (right, bottom),
+    };
+
+    for (x, y, pixel) in mark.enumerate_pixels() {
+        let (x, y) = (left + x, top + y);
+
+        if x >= image.width() || y >= image.height() {
+            continue;
+        }
+
+        let target = image.get_pixel_mut(x, y);
+        let alpha = pixel.0[3] as f64 / 255.0 * opacity.clamp(0.0, 1.0);
+        let under = target.0[3] as f64 / 255.0 * (1.0 - alpha);
+        let coverage = alpha + under;
+
+        if coverage <= 0.0 {
+            continue;
+        }
+
+        for (channel, mark) in target.0[..3].iter_mut().zip(&pixel.0[..3]) {
+            *channel = ((*channel as f64 * under + *mark as f64 * alpha) / coverage).round() as u8;
+        }
+        target.0[3] = (coverage * 255.0).round() as u8;
+    }
+
+    image
+}
@@ -0,0 +1,79 @@
+use project::Failure;
+
+use crate::{
+    angle::Angle,
+    color::Color,
+    layer_lines::LayerLines,
+    lighting::Lighting,
+    material::Material,
+    mesh::Mesh,
+    model::{Model, RenderSettings},
+    view::View,
+};
+
+/// Everything about a render except the mesh, so that many meshes can be
+/// rendered the same way.
+#[derive(Debug, Copy, Clone)]
+pub struct Preset {
+    pub view: View,
+    pub material: Material,
+    pub lighting: Lighting,
+    pub background: Color,
+    pub settings: RenderSettings,
+    /// The crease angle to smooth meshes with, if any.
+    pub smoothing: Option<Angle>,
+    pub layer_lines: Option<LayerLines>,
+    /// Flatten the perspective. See `Camera::orthographic`.
+    pub orthographic: bool,
+}
+
+impl Preset {
+    pub const DEFAULT: Preset = Preset {
+        view: View::Isometric,
+        material: Material::specular(Color::hex(0xb0b0b0), 0.5),
+        lighting: Lighting::Studio,
+        background: Color::hex(0x202020),
+        settings: RenderSettings::DEFAULT,
+        smoothing: None,
+        layer_lines: None,
+        orthographic: false,
+    };
+
+    /// Applies the preset to `mesh`, framing the camera around it.
+    pub fn model(&self, mesh: Mesh) -> Result<Model, Failure> {
+        let mut mesh = mesh.material(self.material);
+        if let Some(crease) = self.smoothing {
+            mesh = mesh.smooth(crease);
+        }
+        if let Some(layer_lines) = self.layer_lines {
+            mesh = mesh.layer_lines(layer_lines);
+        }
+        let analysis = mesh.analyze()?;
+        let mut camera = self.view.camera(&analysis, &self.settings);
+        if self.orthographic {
+            let (min, max) = (analysis.min, analysis.max);
+            camera = camera.orthographic((
+                (min.0 + max.0) / 2.0,
+                (min.1 + max.1) / 2.0,
+                (min.2 + max.2) / 2.0,
+            ));
+        }
+
+        let mut model = Model::new(mesh)
+            .camera(camera)
+            .settings(self.settings)
+            .background(self.background);
+
+        for light in self.lighting.lights() {
+            model = model.add_light(light);
+        }
+
+        Ok(model)
+    }
+}
+
+impl Default for Preset {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
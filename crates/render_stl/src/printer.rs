@@ -0,0 +1,250 @@
+//! What a part is meant to be printed on. Profiles can be loaded from TOML:
+//!
+//! ```toml
+//! name = "My printer"
+//! build_volume = [250, 210, 220]
+//! nozzle_diameter = 0.4
+//! layer_height = { min = 0.05, max = 0.3 }
+//! max_overhang = 45
+//! ```
+//!
+//! Lengths are in mm and `max_overhang` is in degrees from vertical.
+
+use std::{collections::HashSet, path::Path};
+
+use project::{Failure, Nothing, Outcome};
+use rpt::{glm::DVec3, BoundingBox, Triangle};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    angle::Angle, geometry::bounds, location::Position, overhang::OverhangSettings,
+    rotation::Rotation,
+};
+
+// Rotating a mesh leaves rounding errors in its size, which shouldn't stop a
+// part that's exactly as big as the build volume from fitting.
+const FIT_TOLERANCE: f64 = 1e-6;
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LayerHeights {
+    pub min: f64,
+    pub max: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PrinterProfile {
+    pub name: String,
+    /// X, Y and Z, with Z being the build direction.
+    pub build_volume: [f64; 3],
+    pub nozzle_diameter: f64,
+    pub layer_height: LayerHeights,
+    /// The steepest overhang, from vertical, that prints without support.
+    pub max_overhang: Angle,
+}
+
+impl PrinterProfile {
+    fn new(
+        name: &str,
+        build_volume: [f64; 3],
+        layer_height: (f64, f64),
+        max_overhang: f64,
+    ) -> PrinterProfile {
+        PrinterProfile {
+            name: name.to_string(),
+            build_volume,
+            nozzle_diameter: 0.4,
+            layer_height: LayerHeights {
+                min: layer_height.0,
+                max: layer_height.1,
+            },
+            max_overhang: Angle::degrees(max_overhang),
+        }
+    }
+
+    /// A few common printers, with their stock 0.4mm nozzles.
+    pub fn built_in() -> Vec<PrinterProfile> {
+        vec![
+            PrinterProfile::new("prusa-mk4", [250.0, 210.0, 220.0], (0.05, 0.3), 45.0),
+            PrinterProfile::new("prusa-mini", [180.0, 180.0, 180.0], (0.05, 0.25), 45.0),
+            PrinterProfile::new("bambu-x1", [256.0, 256.0, 256.0], (0.08, 0.28), 45.0),
+            PrinterProfile::new("ender-3", [220.0, 220.0, 250.0], (0.1, 0.32), 45.0),
+            PrinterProfile::new("voron-350", [350.0, 350.0, 340.0], (0.1, 0.3), 45.0),
+        ]
+    }
+
+    /// One of the `built_in` profiles.
+    pub fn named(name: &str) -> Result<PrinterProfile, Failure> {
+        let profiles = PrinterProfile::built_in();
+
+        match profiles.iter().position(|profile| profile.name == name) {
+            Some(index) => Ok(profiles[index].clone()),
+            None => {
+                let names: Vec<_> = profiles
+                    .iter()
+                    .map(|profile| profile.name.as_str())
+                    .collect();
+                Err(format!(
+                    "Unknown printer {:?}, expected one of {}",
+                    name,
+                    names.join(", ")
+                )
+                .into())
+            }
+        }
+    }
+
+    pub fn from_toml(source: &str) -> Result<PrinterProfile, Failure> {
+        let profile: PrinterProfile = toml::from_str(source)?;
+        profile.validate()?;
+
+        Ok(profile)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<PrinterProfile, Failure> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| format!("Couldn't read printer {}: {}", path.display(), e))?;
+
+        PrinterProfile::from_toml(&source)
+            .map_err(|e| format!("Invalid printer {}: {}", path.display(), e).into())
+    }
+
+    fn validate(&self) -> Outcome {
+        let positive = |length: f64| length > 0.0 && length.is_finite();
+
+        if !self.build_volume.iter().copied().all(positive) {
+            return Err("The build volume must be positive in every direction".into());
+        }
+
+        if !positive(self.nozzle_diameter) {
+            return Err("The nozzle diameter must be positive".into());
+        }
+
+        let LayerHeights { min, max } = self.layer_height;
+        if !positive(min) || !max.is_finite() || min > max {
+            return Err(format!("Invalid layer height range {} to {}", min, max).into());
+        }
+
+        // Rounded as it's written, so 90 degrees survives the trip through
+        // radians.
+        let overhang = (self.max_overhang.to_degrees() * 1e9).round() / 1e9;
+        if !(0.0..=90.0).contains(&overhang) {
+            return Err(format!(
+                "The max overhang must be 0 to 90 degrees from vertical, not {}",
+                overhang
+            )
+            .into());
+        }
+
+        Ok(Nothing)
+    }
+
+    pub fn supports_layer_height(&self, layer_height: f64) -> bool {
+        (self.layer_height.min..=self.layer_height.max).contains(&layer_height)
+    }
+
+    /// Overhang settings matching what this printer can bridge.
+    pub fn overhang_settings(&self) -> OverhangSettings {
+        OverhangSettings::new(self.max_overhang)
+    }
+
+    pub fn fits(&self, dimensions: Position) -> bool {
+        let [x, y, z] = self.build_volume.map(|size| size + FIT_TOLERANCE);
+
+        dimensions.0 <= x && dimensions.1 <= y && dimensions.2 <= z
+    }
+}
+
+/// Whether a mesh fits a printer's build volume as it is, and which
+/// quarter-turn orientations would.
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildVolumeFit {
+    pub printer: String,
+    /// The mesh's size with its transforms applied.
+    pub dimensions: Position,
+    pub fits: bool,
+    /// Every distinct footprint reachable with quarter turns, whether it
+    /// fits or not.
+    pub orientations: Vec<OrientationFit>,
+}
+
+#[derive(Debug, Copy, Clone, Serialize)]
+pub struct OrientationFit {
+    /// To pass to `Mesh::rotate`, in place of any rotation it already has.
+    pub rotation: Rotation,
+    pub dimensions: Position,
+    pub fits: bool,
+}
+
+impl BuildVolumeFit {
+    /// `transformed` is the mesh as it would be printed. `orient` gives the
+    /// mesh's triangles with a rotation in place of its own.
+    pub(crate) fn of(
+        printer: &PrinterProfile,
+        transformed: &[Triangle],
+        orient: impl Fn(Rotation) -> Vec<Triangle>,
+    ) -> BuildVolumeFit {
+        let dimensions = size(transformed);
+
+        let mut seen = HashSet::new();
+        let orientations = quarter_turns()
+            .into_iter()
+            .map(|rotation| {
+                let dimensions = size(&orient(rotation));
+
+                OrientationFit {
+                    rotation,
+                    dimensions,
+                    fits: printer.fits(dimensions),
+                }
+            })
+            .filter(|fit| {
+                let (x, y, z) = fit.dimensions;
+                let key = |size: f64| (size * 1e6).round() as i64;
+
+                seen.insert((key(x), key(y), key(z)))
+            })
+            .collect();
+
+        BuildVolumeFit {
+            printer: printer.name.clone(),
+            dimensions,
+            fits: printer.fits(dimensions),
+            orientations,
+        }
+    }
+
+    pub fn fitting(&self) -> impl Iterator<Item = &OrientationFit> {
+        self.orientations.iter().filter(|fit| fit.fits)
+    }
+}
+
+fn size(triangles: &[Triangle]) -> Position {
+    if triangles.is_empty() {
+        return (0.0, 0.0, 0.0);
+    }
+
+    let BoundingBox { p_min, p_max } = bounds(triangles);
+    let size: DVec3 = p_max - p_min;
+
+    (size.x, size.y, size.z)
+}
+
+// Each combination swaps a different pair of axes, which between them
+// reach every way of lining the mesh's extents up with the bed.
+fn quarter_turns() -> Vec<Rotation> {
+    let turns = [Angle::ZERO, Angle::degrees(90.0)];
+    let mut rotations = vec![];
+
+    for x in turns {
+        for y in turns {
+            for z in turns {
+                rotations.push(Rotation::euler(x, y, z));
+            }
+        }
+    }
+
+    rotations
+}
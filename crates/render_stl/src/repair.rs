@@ -0,0 +1,371 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use project::Failure;
+use rpt::{glm::DVec3, Triangle};
+use serde::Serialize;
+
+use crate::{analysis::vertex_key, geometry::area_vector};
+
+const DEGENERATE_AREA: f64 = 1e-12;
+
+/// What `repair` changed. All counts are zero for a mesh that was already
+/// clean.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RepairReport {
+    /// Vertex positions that were merged into a nearby vertex.
+    pub welded_vertices: usize,
+    pub removed_degenerate: usize,
+    /// Faces turned over, either to agree with their neighbours or so that
+    /// a closed part faces outwards.
+    pub flipped_faces: usize,
+    pub filled_holes: usize,
+    pub added_triangles: usize,
+}
+
+impl RepairReport {
+    pub fn is_unchanged(&self) -> bool {
+        self.welded_vertices == 0
+            && self.removed_degenerate == 0
+            && self.flipped_faces == 0
+            && self.filled_holes == 0
+            && self.added_triangles == 0
+    }
+}
+
+/// Welds vertices closer than `tolerance`, drops zero-area triangles, makes
+/// winding consistent and outward-facing, fills holes bounded by a single
+/// loop of edges and recomputes normals from the winding. A tolerance of
+/// zero only welds identical vertices.
+pub fn repair(
+    triangles: &[Triangle],
+    tolerance: f64,
+) -> Result<(Vec<Triangle>, RepairReport), Failure> {
+    if tolerance.is_nan() || tolerance < 0.0 {
+        return Err(format!("The weld tolerance can't be negative, not {}", tolerance).into());
+    }
+
+    let mut report = RepairReport::default();
+
+    let (vertices, faces) = weld(triangles, tolerance, &mut report);
+    let mut faces = remove_degenerate(&vertices, faces, &mut report);
+    let mut vertices = vertices;
+    let original = faces.clone();
+
+    make_winding_consistent(&mut faces);
+    fill_holes(&mut vertices, &mut faces, &mut report);
+    orient_outwards(&vertices, &mut faces);
+
+    // A face may be turned over twice, so compare against where it started.
+    report.flipped_faces = original
+        .iter()
+        .zip(&faces)
+        .filter(|(before, after)| before != after)
+        .count();
+
+    let triangles = faces
+        .iter()
+        .map(|[a, b, c]| Triangle::from_vertices(vertices[*a], vertices[*b], vertices[*c]))
+        .collect();
+
+    Ok((triangles, report))
+}
+
+type Face = [usize; 3];
+
+// Snap every vertex to the first earlier vertex within `tolerance`, using a
+// grid of `tolerance`-sized cells so only neighbouring cells are searched.
+// With no tolerance, only identical vertices are the same.
+fn weld(
+    triangles: &[Triangle],
+    tolerance: f64,
+    report: &mut RepairReport,
+) -> (Vec<DVec3>, Vec<Face>) {
+    let cell_size = tolerance.max(f64::EPSILON);
+    let cell = |v: &DVec3| {
+        (
+            (v.x / cell_size).floor() as i64,
+            (v.y / cell_size).floor() as i64,
+            (v.z / cell_size).floor() as i64,
+        )
+    };
+
+    let mut vertices: Vec<DVec3> = vec![];
+    let mut grid: HashMap<(i64, i64, i64), Vec<usize>> = HashMap::new();
+    let mut exact: HashMap<_, usize> = HashMap::new();
+    let mut merged = HashSet::new();
+
+    let mut index_of = |vertex: DVec3| {
+        if tolerance == 0.0 {
+            return *exact.entry(vertex_key(&vertex)).or_insert_with(|| {
+                vertices.push(vertex);
+                vertices.len() - 1
+            });
+        }
+
+        // Cells saturate far from the origin, which is slow but still right
+        // since distances are checked.
+        let (x, y, z) = cell(&vertex);
+
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let neighbour = (
+                        x.saturating_add(dx),
+                        y.saturating_add(dy),
+                        z.saturating_add(dz),
+                    );
+                    let found = grid.get(&neighbour).and_then(|cell| {
+                        cell.iter()
+                            .copied()
+                            .find(|index| (vertices[*index] - vertex).norm() <= tolerance)
+                    });
+
+                    if let Some(index) = found {
+                        if vertices[index] != vertex {
+                            merged.insert(vertex_key(&vertex));
+                        }
+
+                        return index;
+                    }
+                }
+            }
+        }
+
+        vertices.push(vertex);
+        grid.entry((x, y, z)).or_default().push(vertices.len() - 1);
+        vertices.len() - 1
+    };
+
+    let faces = triangles
+        .iter()
+        .map(|triangle| {
+            [
+                index_of(triangle.v1),
+                index_of(triangle.v2),
+                index_of(triangle.v3),
+            ]
+        })
+        .collect();
+
+    report.welded_vertices = merged.len();
+
+    (vertices, faces)
+}
+
+fn remove_degenerate(vertices: &[DVec3], faces: Vec<Face>, report: &mut RepairReport) -> Vec<Face> {
+    let before = faces.len();
+
+    let faces: Vec<Face> = faces
+        .into_iter()
+        .filter(|[a, b, c]| {
+            let triangle = Triangle::from_vertices(vertices[*a], vertices[*b], vertices[*c]);
+
+            a != b && b != c && c != a && area_vector(&triangle).norm() >= DEGENERATE_AREA
+        })
+        .collect();
+
+    report.removed_degenerate = before - faces.len();
+
+    faces
+}
+
+fn directed_edges([a, b, c]: Face) -> [(usize, usize); 3] {
+    [(a, b), (b, c), (c, a)]
+}
+
+fn undirected((from, to): (usize, usize)) -> (usize, usize) {
+    (from.min(to), from.max(to))
+}
+
+fn edge_map(faces: &[Face]) -> HashMap<(usize, usize), Vec<usize>> {
+    let mut edges: HashMap<_, Vec<usize>> = HashMap::new();
+
+    for (index, face) in faces.iter().enumerate() {
+        for edge in directed_edges(*face) {
+            edges.entry(undirected(edge)).or_default().push(index);
+        }
+    }
+
+    edges
+}
+
+fn flip(face: &mut Face) {
+    face.swap(1, 2);
+}
+
+fn has_directed_edge(face: &Face, edge: (usize, usize)) -> bool {
+    directed_edges(*face).contains(&edge)
+}
+
+// Connected groups of faces, joined across manifold edges.
+fn components(faces: &[Face]) -> Vec<Vec<usize>> {
+    let edges = edge_map(faces);
+    let mut seen = vec![false; faces.len()];
+    let mut components = vec![];
+
+    for start in 0..faces.len() {
+        if seen[start] {
+            continue;
+        }
+
+        seen[start] = true;
+        let mut component = vec![];
+        let mut queue = VecDeque::from(vec![start]);
+
+        while let Some(index) = queue.pop_front() {
+            component.push(index);
+
+            for edge in directed_edges(faces[index]) {
+                if let [first, second] = edges[&undirected(edge)].as_slice() {
+                    let neighbour = if *first == index { *second } else { *first };
+
+                    if !seen[neighbour] {
+                        seen[neighbour] = true;
+                        queue.push_back(neighbour);
+                    }
+                }
+            }
+        }
+
+        components.push(component);
+    }
+
+    components
+}
+
+// Walk outwards from each face, turning over any neighbour that traverses
+// a shared edge in the same direction.
+fn make_winding_consistent(faces: &mut [Face]) {
+    let edges = edge_map(faces);
+    let mut seen = vec![false; faces.len()];
+
+    for start in 0..faces.len() {
+        if seen[start] {
+            continue;
+        }
+
+        seen[start] = true;
+        let mut queue = VecDeque::from(vec![start]);
+
+        while let Some(index) = queue.pop_front() {
+            for edge in directed_edges(faces[index]) {
+                if let [first, second] = edges[&undirected(edge)].as_slice() {
+                    let neighbour = if *first == index { *second } else { *first };
+
+                    if seen[neighbour] {
+                        continue;
+                    }
+
+                    seen[neighbour] = true;
+
+                    if has_directed_edge(&faces[neighbour], edge) {
+                        flip(&mut faces[neighbour]);
+                    }
+
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+    }
+}
+
+// A hole is a loop of edges that each belong to a single face. Only loops
+// that never branch are filled, with a fan around their centroid.
+fn fill_holes(vertices: &mut Vec<DVec3>, faces: &mut Vec<Face>, report: &mut RepairReport) {
+    let edges = edge_map(faces);
+    let mut next: HashMap<usize, Vec<usize>> = HashMap::new();
+
+    for face in faces.iter() {
+        for (from, to) in directed_edges(*face) {
+            if edges[&undirected((from, to))].len() == 1 {
+                // The hole runs the opposite way to the face around it.
+                next.entry(to).or_default().push(from);
+            }
+        }
+    }
+
+    let mut visited = HashSet::new();
+    let mut starts: Vec<usize> = next.keys().copied().collect();
+    starts.sort_unstable();
+
+    for start in starts {
+        if visited.contains(&start) {
+            continue;
+        }
+
+        let mut hole = vec![start];
+        let mut current = start;
+        let simple = loop {
+            let to = match next.get(&current).map(Vec::as_slice) {
+                Some([to]) => *to,
+                _ => break false,
+            };
+
+            if to == start {
+                break true;
+            }
+
+            if hole.contains(&to) {
+                break false;
+            }
+
+            hole.push(to);
+            current = to;
+        };
+
+        visited.extend(hole.iter().copied());
+
+        if !simple || hole.len() < 3 {
+            continue;
+        }
+
+        if hole.len() == 3 {
+            faces.push([hole[0], hole[1], hole[2]]);
+            report.added_triangles += 1;
+        } else {
+            let centroid =
+                hole.iter().map(|index| vertices[*index]).sum::<DVec3>() / hole.len() as f64;
+            vertices.push(centroid);
+            let center = vertices.len() - 1;
+
+            for (i, from) in hole.iter().enumerate() {
+                faces.push([center, *from, hole[(i + 1) % hole.len()]]);
+            }
+
+            report.added_triangles += hole.len();
+        }
+
+        report.filled_holes += 1;
+    }
+}
+
+// Turn over any closed part whose signed volume is negative. Parts still
+// open after filling holes have no inside, so they're left as they are.
+fn orient_outwards(vertices: &[DVec3], faces: &mut [Face]) {
+    let edges = edge_map(faces);
+
+    for component in components(faces) {
+        let closed = component.iter().all(|index| {
+            directed_edges(faces[*index])
+                .iter()
+                .all(|edge| edges[&undirected(*edge)].len() == 2)
+        });
+        if !closed {
+            continue;
+        }
+
+        let volume: f64 = component
+            .iter()
+            .map(|index| {
+                let [a, b, c] = faces[*index];
+                vertices[a].dot(&vertices[b].cross(&vertices[c]))
+            })
+            .sum();
+
+        if volume < 0.0 {
+            for index in &component {
+                flip(&mut faces[*index]);
+            }
+        }
+    }
+}
@@ -1,12 +1,29 @@
-use rpt::Bounded;
-
-use crate::{angle::Angle, location::RptPosition};
+use rpt::{
+    glm::{self, DMat4},
+    BoundingBox,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{angle::Angle, geometry::transform_bounds, location::RptPosition};
+
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Convention {
+    #[default]
+    /// Rotate by a fixed angle around the axis `(x, y, z)`.
+    Axis,
+    /// Rotate around the X, then Y, then Z axes, by each angle.
+    Euler,
+}
 
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Rotation {
     x: Angle,
     y: Angle,
     z: Angle,
+    convention: Convention,
 }
 
 impl Rotation {
@@ -14,6 +31,7 @@ impl Rotation {
         x: Angle::ZERO,
         y: Angle::ZERO,
         z: Angle::ZERO,
+        convention: Convention::Axis,
     };
 
     // Related to the golden ratio, computed by hand with some geometry
@@ -27,9 +45,29 @@ impl Rotation {
             x: angle,
             y: angle,
             z: angle,
+            convention: Convention::Axis,
+        }
+    }
+
+    /// Rotates around the X axis, then the Y axis, then the Z axis, about
+    /// the centre of the mesh.
+    pub const fn euler(x: Angle, y: Angle, z: Angle) -> Rotation {
+        Rotation {
+            x,
+            y,
+            z,
+            convention: Convention::Euler,
         }
     }
 
+    pub fn angles(&self) -> (Angle, Angle, Angle) {
+        (self.x, self.y, self.z)
+    }
+
+    pub fn is_euler(&self) -> bool {
+        matches!(self.convention, Convention::Euler)
+    }
+
     pub const fn x(self, x: Angle) -> Rotation {
         Rotation { x, ..self }
     }
@@ -42,29 +80,64 @@ impl Rotation {
         Rotation { z, ..self }
     }
 
-    pub(crate) fn rotate_rpt(
-        self,
-        mesh: rpt::Transformed<rpt::Mesh>,
-    ) -> rpt::Transformed<rpt::Mesh> {
-        let center_before = center(mesh.bounding_box());
+    pub fn is_zero(&self) -> bool {
+        [self.x, self.y, self.z]
+            .iter()
+            .all(|angle| f64::from(*angle) == 0.0)
+    }
 
-        let after = mesh.rotate(
+    // `bounds` is the untransformed bounding box of the mesh that `matrix`
+    // will be applied to.
+    pub(crate) fn rotate_matrix(self, matrix: DMat4, bounds: BoundingBox) -> DMat4 {
+        // Rotating around a zero-length axis produces NaNs all the way down.
+        if self.is_zero() {
+            return matrix;
+        }
+
+        if let Convention::Euler = self.convention {
+            let BoundingBox { p_min, p_max } = transform_bounds(&matrix, bounds);
+            let center = (p_min + p_max) / 2.0;
+
+            return glm::translate(&glm::identity(), &center)
+                * self.euler_matrix()
+                * glm::translate(&glm::identity(), &-center)
+                * matrix;
+        }
+
+        let center_before = center(transform_bounds(&matrix, bounds));
+
+        let after = glm::rotate(
+            &glm::identity(),
             -Rotation::magic(),
-            &rpt::glm::vec3(self.x.into(), self.y.into(), self.z.into()),
-        );
+            &glm::vec3(self.x.into(), self.y.into(), self.z.into()),
+        ) * matrix;
 
-        let center_after = center(after.bounding_box());
+        let center_after = center(transform_bounds(&after, bounds));
 
         // let move_diff = diff(center_before, center_after);
 
-        after
-            .translate(&rpt::glm::vec3(center_before.x - center_after.x, 0.0, 0.0))
-            .translate(&rpt::glm::vec3(
-                0.0,
+        glm::translate(
+            &glm::identity(),
+            &glm::vec3(
+                center_before.x - center_after.x,
                 (center_after.y - center_before.y) * 1.5,
-                0.0,
-            ))
-            .translate(&rpt::glm::vec3(0.0, 0.0, center_after.z - center_before.z))
+                center_after.z - center_before.z,
+            ),
+        ) * after
+    }
+
+    pub(crate) fn euler_matrix(self) -> DMat4 {
+        let identity: DMat4 = glm::identity();
+
+        glm::rotate_z(&identity, self.z.into())
+            * glm::rotate_y(&identity, self.y.into())
+            * glm::rotate_x(&identity, self.x.into())
+    }
+}
+
+impl Default for Rotation {
+    fn default() -> Self {
+        Rotation::ZERO
     }
 }
 
@@ -0,0 +1,325 @@
+//! A TOML description of a render, loaded with `Model::from_toml`.
+//!
+//! ```toml
+//! background = "#202020"
+//!
+//! [camera]
+//! eye = [0, 0, 10]
+//! look_at = [0, 0, 0]
+//! fov = 22.5
+//!
+//! [render]
+//! width = 800
+//! height = 600
+//! samples = 20
+//!
+//! [[mesh]]
+//! path = "models/blok.stl"
+//! scale = 0.1
+//! rotate = { x = 90 }
+//! drop_to_bed = true
+//! material = { color = "#ff0000", roughness = 0.5 }
+//! shading = { overhang = { threshold = 45 } }
+//! smooth = 30
+//! layer_lines = { layer_height = 0.2, relief = 20 }
+//!
+//! [[light]]
+//! kind = "point"
+//! color = "#ffffff"
+//! location = [0, 5, 5]
+//!
+//! [[clip]]
+//! point = [0, 0, 5]
+//! normal = [0, 0, 1]
+//! cap = "#e03030"
+//!
+//! [annotations]
+//! bounding_box = true
+//! dimensions = [{ from = [0, 0, 0], to = [10, 0, 0], label = "Slot" }]
+//! ```
+//!
+//! Angles are in degrees. Mesh paths are relative to the scene file.
+
+use std::path::{Path, PathBuf};
+
+use path_abs::PathFile;
+use project::Failure;
+use serde::Deserialize;
+
+use crate::{
+    angle::Angle,
+    annotate::Annotations,
+    camera::Camera,
+    clipping::ClippingPlane,
+    color::Color,
+    direction::Direction,
+    layer_lines::LayerLines,
+    light_source::LightSource,
+    location::{Location, Position},
+    material::Material,
+    mesh::{Mesh, MeshSource, Shading},
+    model::{Model, RenderSettings},
+    overhang::OverhangSettings,
+    rotation::Rotation,
+};
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneDescription {
+    #[serde(default)]
+    pub background: Option<Color>,
+    #[serde(default)]
+    pub camera: CameraDescription,
+    #[serde(default)]
+    pub render: RenderSettings,
+    #[serde(default, rename = "mesh")]
+    pub meshes: Vec<MeshDescription>,
+    #[serde(default, rename = "light")]
+    pub lights: Vec<LightSource>,
+    #[serde(default, rename = "clip")]
+    pub clipping: Vec<ClippingPlane>,
+    #[serde(default)]
+    pub annotations: Option<Annotations>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraDescription {
+    pub eye: Option<[f64; 3]>,
+    pub direction: Option<[f64; 3]>,
+    pub look_at: Option<[f64; 3]>,
+    pub up: Option<[f64; 3]>,
+    pub fov: Option<f64>,
+    pub aperture: Option<f64>,
+    pub focal_distance: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MeshDescription {
+    pub path: PathBuf,
+    pub scale: Option<f64>,
+    pub rotate: Option<RotationDescription>,
+    pub translate: Option<[f64; 3]>,
+    #[serde(default)]
+    pub drop_to_bed: bool,
+    pub material: Option<MaterialDescription>,
+    pub shading: Option<ShadingDescription>,
+    /// The crease angle, in degrees, to smooth the mesh with.
+    pub smooth: Option<f64>,
+    /// Shade the mesh as if FDM printed.
+    pub layer_lines: Option<LayerLines>,
+}
+
+/// Euler angles, applied around X, then Y, then Z.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RotationDescription {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MaterialDescription {
+    pub color: Color,
+    #[serde(default = "MaterialDescription::default_roughness")]
+    pub roughness: f64,
+    pub metallic: Option<f64>,
+    pub emittance: Option<f64>,
+    pub refraction: Option<f64>,
+    #[serde(default)]
+    pub transparent: bool,
+}
+
+impl MaterialDescription {
+    fn default_roughness() -> f64 {
+        0.5
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum ShadingDescription {
+    Material,
+    Overhang {
+        threshold: Option<f64>,
+        build_direction: Option<Direction>,
+    },
+}
+
+impl SceneDescription {
+    pub fn load(path: impl AsRef<Path>) -> Result<SceneDescription, Failure> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| format!("Couldn't read scene {}: {}", path.display(), e))?;
+
+        toml::from_str(&source)
+            .map_err(|e| format!("Invalid scene {}: {}", path.display(), e).into())
+    }
+
+    /// Builds a model, resolving mesh paths relative to `root`.
+    pub fn into_model(self, root: &Path) -> Result<Model, Failure> {
+        let mut meshes = self
+            .meshes
+            .into_iter()
+            .enumerate()
+            .map(|(index, mesh)| {
+                mesh.into_mesh(root)
+                    .map_err(|e| format!("[[mesh]] #{}: {}", index + 1, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter();
+
+        let mut model = match meshes.next() {
+            Some(mesh) => Model::new(mesh),
+            None => return Err("Scene has no [[mesh]] entries".into()),
+        };
+
+        for mesh in meshes {
+            model = model.add_mesh(mesh);
+        }
+
+        for light in self.lights {
+            model = model.add_light(light);
+        }
+
+        for plane in self.clipping {
+            model = model.clip(plane);
+        }
+
+        if let Some(background) = self.background {
+            model = model.background(background);
+        }
+
+        if let Some(annotations) = self.annotations {
+            model = model.annotate(annotations);
+        }
+
+        Ok(model
+            .camera(self.camera.into_camera()?)
+            .settings(self.render))
+    }
+}
+
+impl CameraDescription {
+    fn into_camera(self) -> Result<Camera, Failure> {
+        if self.direction.is_some() && self.look_at.is_some() {
+            return Err("The camera can have a direction or look_at, but not both".into());
+        }
+
+        let mut camera = Camera::default();
+
+        if let Some(eye) = self.eye {
+            camera = camera.eye(position(eye));
+        }
+        if let Some(direction) = self.direction {
+            camera = camera.direction(position(direction));
+        }
+        if let Some(target) = self.look_at {
+            camera = camera.look_at(position(target));
+        }
+        if let Some(up) = self.up {
+            camera = camera.up(position(up));
+        }
+        if let Some(fov) = self.fov {
+            camera = camera.fov(Angle::degrees(fov));
+        }
+        if let Some(aperture) = self.aperture {
+            camera = camera.aperture(aperture);
+        }
+        if let Some(focal_distance) = self.focal_distance {
+            camera = camera.focal_distance(focal_distance);
+        }
+
+        Ok(camera)
+    }
+}
+
+impl MeshDescription {
+    fn into_mesh(self, root: &Path) -> Result<Mesh, Failure> {
+        let path = root.join(&self.path);
+        let file =
+            PathFile::new(&path).map_err(|e| format!("Couldn't find {}: {}", path.display(), e))?;
+
+        let mut mesh = Mesh::new(MeshSource::DynamicFile(file));
+
+        if let Some(scale) = self.scale {
+            mesh = mesh.scale(scale);
+        }
+        if let Some(RotationDescription { x, y, z }) = self.rotate {
+            mesh = mesh.rotate(Rotation::euler(
+                Angle::degrees(x),
+                Angle::degrees(y),
+                Angle::degrees(z),
+            ));
+        }
+        if let Some([x, y, z]) = self.translate {
+            mesh = mesh.translate(Location::new(x, y, z));
+        }
+        if self.drop_to_bed {
+            mesh = mesh.drop_to_bed();
+        }
+        if let Some(material) = self.material {
+            mesh = mesh.material(material.into_material());
+        }
+        if let Some(shading) = self.shading {
+            mesh = mesh.shading(shading.into_shading());
+        }
+        if let Some(crease) = self.smooth {
+            mesh = mesh.smooth(Angle::degrees(crease));
+        }
+        if let Some(layer_lines) = self.layer_lines {
+            mesh = mesh.layer_lines(layer_lines);
+        }
+
+        Ok(mesh)
+    }
+}
+
+impl MaterialDescription {
+    fn into_material(self) -> Material {
+        let mut material =
+            Material::specular(self.color, self.roughness).transparent(self.transparent);
+
+        if let Some(metallic) = self.metallic {
+            material = material.metallic(metallic);
+        }
+        if let Some(emittance) = self.emittance {
+            material = material.emittance(emittance);
+        }
+        if let Some(refraction) = self.refraction {
+            material = material.refraction(refraction);
+        }
+
+        material
+    }
+}
+
+impl ShadingDescription {
+    fn into_shading(self) -> Shading {
+        match self {
+            ShadingDescription::Material => Shading::Material,
+            ShadingDescription::Overhang {
+                threshold,
+                build_direction,
+            } => {
+                let mut settings = match threshold {
+                    Some(threshold) => OverhangSettings::new(Angle::degrees(threshold)),
+                    None => OverhangSettings::default(),
+                };
+
+                if let Some(direction) = build_direction {
+                    settings = settings.build_direction(direction);
+                }
+
+                Shading::Overhang(settings)
+            }
+        }
+    }
+}
+
+fn position([x, y, z]: [f64; 3]) -> Position {
+    (x, y, z)
+}
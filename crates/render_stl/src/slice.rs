@@ -0,0 +1,472 @@
+//! Slicer-style layer outlines, for previewing how a part builds up without
+//! running a real slicer. Layers are cut horizontally through the middle of
+//! each layer height, the way slicers do, and are assumed to stack along +Z.
+
+use std::{collections::HashMap, io::Write, time::Duration};
+
+use project::{Failure, Nothing, Outcome};
+use rpt::{
+    glm::DVec3,
+    image::{
+        codecs::gif::{GifEncoder, Repeat},
+        Delay, DynamicImage, Frame, Rgb, RgbImage,
+    },
+    BoundingBox, Triangle,
+};
+
+use crate::{
+    color::Color,
+    geometry::{area_vector, bounds},
+    model::encode,
+};
+
+/// The most layers `Slices::of` will cut, far more than any print needs but
+/// few enough to fit in memory.
+pub const MAX_LAYERS: usize = 100_000;
+
+/// How `Slices` draws a layer.
+#[derive(Debug, Copy, Clone)]
+pub struct SlicePreview {
+    pub width: u32,
+    pub height: u32,
+    /// Pixels left empty around the widest layer.
+    pub margin: u32,
+    pub background: Color,
+    pub fill: Color,
+    /// The layer underneath is drawn in this colour, to show what the
+    /// current one rests on.
+    pub below: Option<Color>,
+}
+
+impl SlicePreview {
+    pub const DEFAULT: SlicePreview = SlicePreview {
+        width: 400,
+        height: 400,
+        margin: 10,
+        background: Color::hex(0x202020),
+        fill: Color::hex(0xffa000),
+        below: Some(Color::hex(0x505050)),
+    };
+
+    pub fn size(self, width: u32, height: u32) -> SlicePreview {
+        SlicePreview {
+            width,
+            height,
+            ..self
+        }
+    }
+
+    pub fn colors(self, background: impl Into<Color>, fill: impl Into<Color>) -> SlicePreview {
+        SlicePreview {
+            background: background.into(),
+            fill: fill.into(),
+            ..self
+        }
+    }
+
+    pub fn below(self, below: Option<Color>) -> SlicePreview {
+        SlicePreview { below, ..self }
+    }
+}
+
+impl Default for SlicePreview {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// A closed outline in a layer. Outer boundaries run anticlockwise and holes
+/// clockwise, seen from above, as long as the mesh faces outwards.
+#[derive(Debug, Clone)]
+pub struct Polygon {
+    pub points: Vec<(f64, f64)>,
+}
+
+impl Polygon {
+    /// Positive for outer boundaries and negative for holes.
+    pub fn signed_area(&self) -> f64 {
+        let points = &self.points;
+
+        (0..points.len())
+            .map(|i| {
+                let (a, b) = (points[i], points[(i + 1) % points.len()]);
+                a.0 * b.1 - b.0 * a.1
+            })
+            .sum::<f64>()
+            / 2.0
+    }
+
+    pub fn is_hole(&self) -> bool {
+        self.signed_area() < 0.0
+    }
+
+    fn edges(&self) -> impl Iterator<Item = ((f64, f64), (f64, f64))> + '_ {
+        let points = &self.points;
+
+        (0..points.len()).map(move |i| (points[i], points[(i + 1) % points.len()]))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Layer {
+    /// The height the layer was cut at.
+    pub z: f64,
+    pub polygons: Vec<Polygon>,
+    /// Chains of segments that didn't close, which happens where the mesh
+    /// has holes. They aren't included in `polygons`.
+    pub open_chains: usize,
+}
+
+impl Layer {
+    /// The area printed in this layer.
+    pub fn area(&self) -> f64 {
+        self.polygons.iter().map(Polygon::signed_area).sum()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Slices {
+    pub layer_height: f64,
+    pub layers: Vec<Layer>,
+    // The XY bounds of the mesh, so every frame is drawn at the same scale.
+    min: (f64, f64),
+    max: (f64, f64),
+}
+
+impl Slices {
+    /// Cuts `triangles` every `layer_height`, starting half a layer above
+    /// the lowest point. Fails for more than `MAX_LAYERS` layers.
+    pub fn of(triangles: &[Triangle], layer_height: f64) -> Result<Slices, Failure> {
+        if !layer_height.is_finite() || layer_height <= 0.0 {
+            return Err(format!("Invalid layer height {}", layer_height).into());
+        }
+
+        let BoundingBox { p_min, p_max } = bounds(triangles);
+        let (bottom, top) = (p_min.z, p_max.z);
+
+        let count = if top > bottom {
+            ((top - bottom) / layer_height).ceil()
+        } else {
+            0.0
+        };
+        if count.is_nan() || count > MAX_LAYERS as f64 {
+            return Err(format!(
+                "Slicing {} high every {} would make {} layers, more than {}",
+                top - bottom,
+                layer_height,
+                count,
+                MAX_LAYERS
+            )
+            .into());
+        }
+        let count = count as usize;
+
+        let layers = (0..count)
+            .map(|index| {
+                let z = bottom + layer_height * (index as f64 + 0.5);
+                slice_layer(triangles, z.min(top))
+            })
+            .collect();
+
+        Ok(Slices {
+            layer_height,
+            layers,
+            min: (p_min.x, p_min.y),
+            max: (p_max.x, p_max.y),
+        })
+    }
+
+    /// Draws a layer, filling its polygons even-odd.
+    pub fn render_layer(&self, index: usize, preview: &SlicePreview) -> RgbImage {
+        let mut image =
+            RgbImage::from_pixel(preview.width, preview.height, pixel(preview.background));
+
+        let layer_below = index
+            .checked_sub(1)
+            .and_then(|below| self.layers.get(below));
+        if let (Some(below), Some(layer)) = (preview.below, layer_below) {
+            self.fill_layer(&mut image, layer, preview, below);
+        }
+
+        if let Some(layer) = self.layers.get(index) {
+            self.fill_layer(&mut image, layer, preview, preview.fill);
+        }
+
+        image
+    }
+
+    pub fn write_png(
+        &self,
+        index: usize,
+        preview: &SlicePreview,
+        target: &mut impl Write,
+    ) -> Outcome {
+        encode(
+            DynamicImage::ImageRgb8(self.render_layer(index, preview)),
+            target,
+        )
+    }
+
+    /// Writes a layer as an SVG in mesh units, with +Y up.
+    pub fn write_svg(
+        &self,
+        index: usize,
+        preview: &SlicePreview,
+        target: &mut impl Write,
+    ) -> Outcome {
+        let layer = self
+            .layers
+            .get(index)
+            .ok_or_else(|| format!("There's no layer {}", index))?;
+        // A mesh with no width or depth, like a wall seen edge on, is given
+        // some around its middle so the view box isn't empty.
+        let size = (self.max.0 - self.min.0, self.max.1 - self.min.1);
+        let least = size.0.max(size.1).max(self.layer_height);
+        let (width, height) = (
+            if size.0 > 0.0 { size.0 } else { least },
+            if size.1 > 0.0 { size.1 } else { least },
+        );
+        let (left, top) = (
+            self.min.0 - (width - size.0) / 2.0,
+            -self.max.1 - (height - size.1) / 2.0,
+        );
+
+        writeln!(
+            target,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="{} {} {} {}">"#,
+            preview.width, preview.height, left, top, width, height
+        )?;
+        writeln!(
+            target,
+            r#"  <rect x="{}" y="{}" width="{}" height="{}" fill="{}"/>"#,
+            left,
+            top,
+            width,
+            height,
+            hex(preview.background)
+        )?;
+        writeln!(target, r#"  <g transform="scale(1, -1)">"#)?;
+
+        if !layer.polygons.is_empty() {
+            let path: Vec<String> = layer
+                .polygons
+                .iter()
+                .map(|polygon| {
+                    let points: Vec<String> = polygon
+                        .points
+                        .iter()
+                        .map(|(x, y)| format!("{} {}", x, y))
+                        .collect();
+                    format!("M {} Z", points.join(" L "))
+                })
+                .collect();
+
+            writeln!(
+                target,
+                r#"    <path fill="{}" fill-rule="evenodd" d="{}"/>"#,
+                hex(preview.fill),
+                path.join(" ")
+            )?;
+        }
+
+        writeln!(target, "  </g>\n</svg>")?;
+
+        Ok(Nothing)
+    }
+
+    /// Writes every layer, bottom to top, as a looping GIF with `delay`
+    /// between frames.
+    pub fn write_gif(
+        &self,
+        preview: &SlicePreview,
+        delay: Duration,
+        target: &mut impl Write,
+    ) -> Outcome {
+        let mut encoder = GifEncoder::new(target);
+        encoder.set_repeat(Repeat::Infinite)?;
+
+        let delay = Delay::from_saturating_duration(delay);
+        let frames = (0..self.layers.len()).map(|index| {
+            let image = rpt::image::DynamicImage::ImageRgb8(self.render_layer(index, preview));
+            Frame::from_parts(image.into_rgba8(), 0, 0, delay)
+        });
+
+        encoder.encode_frames(frames)?;
+
+        Ok(Nothing)
+    }
+
+    // Scanline fill at pixel centres, with the mesh's XY bounds fitted
+    // inside the margin.
+    fn fill_layer(
+        &self,
+        image: &mut RgbImage,
+        layer: &Layer,
+        preview: &SlicePreview,
+        color: Color,
+    ) {
+        let (width, height) = (image.width() as f64, image.height() as f64);
+        let margin = preview.margin as f64;
+        let size = (
+            (self.max.0 - self.min.0).max(f64::EPSILON),
+            (self.max.1 - self.min.1).max(f64::EPSILON),
+        );
+        let scale = ((width - 2.0 * margin) / size.0)
+            .min((height - 2.0 * margin) / size.1)
+            .max(0.0);
+        let offset = (
+            (width - size.0 * scale) / 2.0,
+            (height - size.1 * scale) / 2.0,
+        );
+
+        let to_image = |(x, y): (f64, f64)| {
+            (
+                offset.0 + (x - self.min.0) * scale,
+                height - offset.1 - (y - self.min.1) * scale,
+            )
+        };
+        let edges: Vec<_> = layer
+            .polygons
+            .iter()
+            .flat_map(Polygon::edges)
+            .map(|(from, to)| (to_image(from), to_image(to)))
+            .collect();
+
+        for row in 0..image.height() {
+            let y = row as f64 + 0.5;
+            let mut crossings: Vec<f64> = edges
+                .iter()
+                .filter(|(from, to)| (from.1 <= y) != (to.1 <= y))
+                .map(|(from, to)| from.0 + (to.0 - from.0) * (y - from.1) / (to.1 - from.1))
+                .collect();
+            crossings.sort_by(f64::total_cmp);
+
+            for span in crossings.chunks_exact(2) {
+                let start = (span[0] - 0.5).ceil().max(0.0) as u32;
+                let end = (span[1] - 0.5).floor().min(width - 1.0);
+
+                if end < 0.0 {
+                    continue;
+                }
+
+                for column in start..=end as u32 {
+                    image.put_pixel(column, row, pixel(color));
+                }
+            }
+        }
+    }
+}
+
+type Key = (u64, u64);
+
+fn key((x, y): (f64, f64)) -> Key {
+    (x.to_bits(), y.to_bits())
+}
+
+// Each triangle that crosses `z` contributes a segment, oriented so the
+// inside of the part is on its left. Segments are then chained end to end.
+fn slice_layer(triangles: &[Triangle], z: f64) -> Layer {
+    let segments: Vec<_> = triangles
+        .iter()
+        .filter_map(|triangle| segment(triangle, z))
+        .collect();
+
+    let mut starting_at: HashMap<Key, Vec<usize>> = HashMap::new();
+    for (index, (from, _)) in segments.iter().enumerate() {
+        starting_at.entry(key(*from)).or_default().push(index);
+    }
+
+    let mut used = vec![false; segments.len()];
+    let mut polygons = vec![];
+    let mut open_chains = 0;
+
+    for start in 0..segments.len() {
+        if used[start] {
+            continue;
+        }
+
+        used[start] = true;
+        let mut points = vec![segments[start].0];
+        let mut end = segments[start].1;
+
+        let closed = loop {
+            if key(end) == key(points[0]) {
+                break true;
+            }
+
+            let next = starting_at
+                .get(&key(end))
+                .and_then(|candidates| candidates.iter().copied().find(|index| !used[*index]));
+
+            match next {
+                Some(next) => {
+                    used[next] = true;
+                    points.push(end);
+                    end = segments[next].1;
+                }
+                None => break false,
+            }
+        };
+
+        if closed && points.len() >= 3 {
+            polygons.push(Polygon { points });
+        } else if !closed {
+            open_chains += 1;
+        }
+    }
+
+    Layer {
+        z,
+        polygons,
+        open_chains,
+    }
+}
+
+fn segment(triangle: &Triangle, z: f64) -> Option<((f64, f64), (f64, f64))> {
+    let vertices = [triangle.v1, triangle.v2, triangle.v3];
+    let below = vertices.map(|vertex| vertex.z < z);
+
+    let crossings: Vec<(f64, f64)> = (0..3)
+        .filter(|i| below[*i] != below[(i + 1) % 3])
+        .map(|i| crossing(vertices[i], vertices[(i + 1) % 3], z))
+        .collect();
+
+    let (from, to) = match crossings.as_slice() {
+        [from, to] if key(*from) != key(*to) => (*from, *to),
+        _ => return None,
+    };
+
+    // Walking anticlockwise around the part, the outward normal points to
+    // the right.
+    let normal = area_vector(triangle);
+    let along = (to.0 - from.0) * -normal.y + (to.1 - from.1) * normal.x;
+
+    if along >= 0.0 {
+        Some((from, to))
+    } else {
+        Some((to, from))
+    }
+}
+
+// Neighbouring triangles traverse a shared edge in opposite directions, so
+// interpolate from the same end either way to get identical points.
+fn crossing(a: DVec3, b: DVec3, z: f64) -> (f64, f64) {
+    let (a, b) = if (a.x, a.y, a.z) <= (b.x, b.y, b.z) {
+        (a, b)
+    } else {
+        (b, a)
+    };
+    let t = (z - a.z) / (b.z - a.z);
+
+    (a.x + (b.x - a.x) * t, a.y + (b.y - a.y) * t)
+}
+
+fn pixel(color: Color) -> Rgb<u8> {
+    let (r, g, b) = color.rgb();
+    Rgb([r, g, b])
+}
+
+fn hex(color: Color) -> String {
+    let (r, g, b) = color.rgb();
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
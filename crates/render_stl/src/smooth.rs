@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+
+use rpt::{glm::DVec3, Triangle};
+
+use crate::{analysis::vertex_key, angle::Angle, geometry::area_vector};
+
+/// Gives each corner the average normal of the faces around it, leaving out
+/// any face that meets this one at more than `crease`, so curved surfaces
+/// shade smoothly while sharp edges stay sharp.
+///
+/// Faces are weighted by their angle at the corner, so how a surface was
+/// split into triangles doesn't tilt the result.
+pub fn smooth_normals(triangles: &[Triangle], crease: Angle) -> Vec<Triangle> {
+    let normals: Vec<Option<DVec3>> = triangles
+        .iter()
+        .map(|triangle| {
+            let area = area_vector(triangle);
+            (area.norm() > 0.0).then(|| area.normalize())
+        })
+        .collect();
+
+    let mut around: HashMap<_, Vec<(usize, f64)>> = HashMap::new();
+    for (index, triangle) in triangles.iter().enumerate() {
+        let Triangle { v1, v2, v3, .. } = *triangle;
+
+        for (vertex, a, b) in [(v1, v2, v3), (v2, v3, v1), (v3, v1, v2)] {
+            around
+                .entry(vertex_key(&vertex))
+                .or_default()
+                .push((index, corner_angle(vertex, a, b)));
+        }
+    }
+
+    let crease: f64 = crease.into();
+    let crease = crease.cos();
+
+    triangles
+        .iter()
+        .enumerate()
+        .map(|(index, triangle)| {
+            // Degenerate faces have no direction to average with.
+            let normal = match normals[index] {
+                Some(normal) => normal,
+                None => return *triangle,
+            };
+
+            let corner = |vertex: &DVec3| {
+                around[&vertex_key(vertex)]
+                    .iter()
+                    .filter_map(|(other, angle)| {
+                        let other = normals[*other]?;
+                        (other.dot(&normal) >= crease).then(|| other * *angle)
+                    })
+                    // Rounding can leave a face out of its own average at
+                    // a crease of 0, so start from a trace of its normal.
+                    .fold(normal * f64::EPSILON, |sum, normal| sum + normal)
+                    .normalize()
+            };
+
+            Triangle {
+                n1: corner(&triangle.v1),
+                n2: corner(&triangle.v2),
+                n3: corner(&triangle.v3),
+                ..*triangle
+            }
+        })
+        .collect()
+}
+
+fn corner_angle(vertex: DVec3, a: DVec3, b: DVec3) -> f64 {
+    let (a, b) = (a - vertex, b - vertex);
+
+    a.angle(&b)
+}
@@ -0,0 +1,232 @@
+use std::io::{self, Read, Write};
+
+use project::{Nothing, Outcome};
+use rpt::{glm::DVec3, Triangle};
+
+use crate::geometry::area_vector;
+
+const DEFAULT_HEADER: &str = "render_stl";
+
+pub enum StlFormat {
+    /// `header` is truncated to the 80 bytes the format allows.
+    Binary { header: String },
+    /// `header` is used as the solid's name.
+    Ascii { header: String },
+}
+
+impl StlFormat {
+    pub fn binary() -> StlFormat {
+        StlFormat::Binary {
+            header: DEFAULT_HEADER.to_string(),
+        }
+    }
+
+    pub fn ascii() -> StlFormat {
+        StlFormat::Ascii {
+            header: DEFAULT_HEADER.to_string(),
+        }
+    }
+
+    pub fn header(self, header: impl Into<String>) -> StlFormat {
+        let header = header.into();
+
+        match self {
+            StlFormat::Binary { .. } => StlFormat::Binary { header },
+            StlFormat::Ascii { .. } => StlFormat::Ascii { header },
+        }
+    }
+}
+
+// Reads an STL file into raw triangles. This mirrors `rpt::load_stl`, but
+// keeps the triangles around so they can be measured and checked before
+// they disappear into a kd-tree.
+pub fn read_stl(mut reader: impl Read) -> io::Result<Vec<Triangle>> {
+    let mut bytes = vec![];
+    reader.read_to_end(&mut bytes)?;
+
+    if bytes.len() < 15 {
+        return Err(invalid_data("Loaded .STL file is too short"));
+    }
+
+    if bytes.len() >= 84 {
+        let count = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize;
+
+        if bytes.len() == 84 + count * 50 {
+            return Ok(read_binary(&bytes[84..], count));
+        }
+    }
+
+    if bytes.starts_with(b"solid") {
+        read_ascii(&String::from_utf8_lossy(&bytes))
+    } else {
+        Err(invalid_data(
+            "Loaded .STL file, but could not determine format",
+        ))
+    }
+}
+
+fn read_binary(bytes: &[u8], count: usize) -> Vec<Triangle> {
+    let read_f64 = |offset: usize| {
+        f32::from_le_bytes([
+            bytes[offset],
+            bytes[offset + 1],
+            bytes[offset + 2],
+            bytes[offset + 3],
+        ]) as f64
+    };
+    let read_vec3 =
+        |offset: usize| DVec3::new(read_f64(offset), read_f64(offset + 4), read_f64(offset + 8));
+
+    (0..count)
+        .map(|i| {
+            let offset = i * 50;
+
+            triangle(
+                read_vec3(offset),
+                read_vec3(offset + 12),
+                read_vec3(offset + 24),
+                read_vec3(offset + 36),
+            )
+        })
+        .collect()
+}
+
+fn read_ascii(source: &str) -> io::Result<Vec<Triangle>> {
+    let mut tokens = source.split_ascii_whitespace();
+    let mut triangles = vec![];
+    let mut normal = DVec3::zeros();
+    let mut vertices = vec![];
+
+    while let Some(token) = tokens.next() {
+        match token {
+            "normal" => normal = read_ascii_vec3(&mut tokens)?,
+            "vertex" => vertices.push(read_ascii_vec3(&mut tokens)?),
+            "endfacet" => {
+                if vertices.len() != 3 {
+                    return Err(invalid_data(format!(
+                        "Malformed STL file: expected 3 vertices in a facet, found {}",
+                        vertices.len()
+                    )));
+                }
+
+                triangles.push(triangle(normal, vertices[0], vertices[1], vertices[2]));
+                vertices.clear();
+            }
+            _ => {}
+        }
+    }
+
+    Ok(triangles)
+}
+
+fn read_ascii_vec3<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> io::Result<DVec3> {
+    let mut next = || {
+        tokens
+            .next()
+            .and_then(|token| token.parse::<f64>().ok())
+            .ok_or_else(|| invalid_data("Malformed STL file: expected a number"))
+    };
+
+    Ok(DVec3::new(next()?, next()?, next()?))
+}
+
+// Many exporters write a zero normal and expect readers to infer it from
+// the winding, so only trust the stored normal when it's usable.
+fn triangle(normal: DVec3, v1: DVec3, v2: DVec3, v3: DVec3) -> Triangle {
+    if normal.norm_squared() == 0.0 {
+        Triangle::from_vertices(v1, v2, v3)
+    } else {
+        Triangle {
+            v1,
+            v2,
+            v3,
+            n1: normal,
+            n2: normal,
+            n3: normal,
+        }
+    }
+}
+
+/// Writes triangles as STL. Facet normals are recomputed from the winding
+/// rather than taken from the triangles.
+pub fn write_stl(triangles: &[Triangle], target: &mut impl Write, format: &StlFormat) -> Outcome {
+    match format {
+        StlFormat::Binary { header } => write_binary(triangles, target, header),
+        StlFormat::Ascii { header } => write_ascii(triangles, target, header),
+    }
+}
+
+fn write_binary(triangles: &[Triangle], target: &mut impl Write, header: &str) -> Outcome {
+    let mut bytes = [0u8; 80];
+    let header = header.as_bytes();
+    let length = header.len().min(80);
+    bytes[..length].copy_from_slice(&header[..length]);
+
+    target.write_all(&bytes)?;
+    target.write_all(&(triangles.len() as u32).to_le_bytes())?;
+
+    for triangle in triangles {
+        for vector in &[
+            facet_normal(triangle),
+            triangle.v1,
+            triangle.v2,
+            triangle.v3,
+        ] {
+            for n in vector.iter() {
+                target.write_all(&(*n as f32).to_le_bytes())?;
+            }
+        }
+
+        // Attribute byte count, unused.
+        target.write_all(&[0, 0])?;
+    }
+
+    Ok(Nothing)
+}
+
+fn write_ascii(triangles: &[Triangle], target: &mut impl Write, name: &str) -> Outcome {
+    let name = name.replace(char::is_whitespace, "_");
+
+    writeln!(target, "solid {}", name)?;
+
+    for triangle in triangles {
+        let normal = facet_normal(triangle);
+
+        writeln!(
+            target,
+            "  facet normal {:e} {:e} {:e}",
+            normal.x, normal.y, normal.z
+        )?;
+        writeln!(target, "    outer loop")?;
+
+        for vertex in &[triangle.v1, triangle.v2, triangle.v3] {
+            writeln!(
+                target,
+                "      vertex {:e} {:e} {:e}",
+                vertex.x, vertex.y, vertex.z
+            )?;
+        }
+
+        writeln!(target, "    endloop")?;
+        writeln!(target, "  endfacet")?;
+    }
+
+    writeln!(target, "endsolid {}", name)?;
+
+    Ok(Nothing)
+}
+
+fn facet_normal(triangle: &Triangle) -> DVec3 {
+    let area_vector = area_vector(triangle);
+    let length = area_vector.norm();
+
+    if length > 0.0 {
+        area_vector / length
+    } else {
+        DVec3::zeros()
+    }
+}
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
@@ -0,0 +1,149 @@
+use std::io::{Cursor, Write};
+
+use project::{Nothing, Outcome};
+use zip::{write::FileOptions, ZipWriter};
+
+use crate::{geometry::index, part::Part};
+
+const CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">
+  <Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml" />
+  <Default Extension="model" ContentType="application/vnd.ms-package.3dmanufacturing-3dmodel+xml" />
+</Types>
+"#;
+
+const RELATIONSHIPS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+  <Relationship Target="/3D/3dmodel.model" Id="rel0" Type="http://schemas.microsoft.com/3dmanufacturing/2013/01/3dmodel" />
+</Relationships>
+"#;
+
+#[derive(Debug, Copy, Clone, Default)]
+pub enum Unit {
+    Micron,
+    #[default]
+    Millimeter,
+    Centimeter,
+    Inch,
+    Foot,
+    Meter,
+}
+
+impl Unit {
+    fn as_str(self) -> &'static str {
+        match self {
+            Unit::Micron => "micron",
+            Unit::Millimeter => "millimeter",
+            Unit::Centimeter => "centimeter",
+            Unit::Inch => "inch",
+            Unit::Foot => "foot",
+            Unit::Meter => "meter",
+        }
+    }
+}
+
+/// Writes parts as a 3MF package, with one named, coloured object per part.
+/// There must be at least one part, and every part needs triangles, since
+/// 3MF doesn't allow empty models or meshes.
+pub fn write_3mf(parts: &[Part], target: &mut impl Write, unit: Unit) -> Outcome {
+    if parts.is_empty() {
+        return Err("There are no parts to write to 3MF".into());
+    }
+
+    if let Some(part) = parts.iter().find(|part| part.triangles.is_empty()) {
+        return Err(format!("Part {:?} has no triangles to write to 3MF", part.name).into());
+    }
+
+    // The zip writer needs to seek, so build the package in memory.
+    let mut package = ZipWriter::new(Cursor::new(vec![]));
+    let options = FileOptions::default();
+
+    package.start_file("[Content_Types].xml", options)?;
+    package.write_all(CONTENT_TYPES.as_bytes())?;
+
+    package.start_file("_rels/.rels", options)?;
+    package.write_all(RELATIONSHIPS.as_bytes())?;
+
+    package.start_file("3D/3dmodel.model", options)?;
+    write_model(parts, &mut package, unit)?;
+
+    target.write_all(&package.finish()?.into_inner())?;
+
+    Ok(Nothing)
+}
+
+fn write_model(parts: &[Part], target: &mut impl Write, unit: Unit) -> Outcome {
+    writeln!(target, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        target,
+        r#"<model unit="{}" xml:lang="en-US" xmlns="http://schemas.microsoft.com/3dmanufacturing/core/2015/02">"#,
+        unit.as_str()
+    )?;
+    writeln!(target, "  <resources>")?;
+
+    // Resource 1 holds a colour per part; objects are numbered from 2.
+    writeln!(target, r#"    <basematerials id="1">"#)?;
+    for part in parts {
+        let (red, green, blue) = part.color.rgb();
+
+        writeln!(
+            target,
+            r##"      <base name="{}" displaycolor="#{:02X}{:02X}{:02X}" />"##,
+            escape(&part.name),
+            red,
+            green,
+            blue
+        )?;
+    }
+    writeln!(target, "    </basematerials>")?;
+
+    for (i, part) in parts.iter().enumerate() {
+        let (vertices, faces) = index(&part.triangles);
+
+        writeln!(
+            target,
+            r#"    <object id="{}" type="model" name="{}" pid="1" pindex="{}">"#,
+            i + 2,
+            escape(&part.name),
+            i
+        )?;
+        writeln!(target, "      <mesh>")?;
+        writeln!(target, "        <vertices>")?;
+        for vertex in &vertices {
+            writeln!(
+                target,
+                r#"          <vertex x="{}" y="{}" z="{}" />"#,
+                vertex.x, vertex.y, vertex.z
+            )?;
+        }
+        writeln!(target, "        </vertices>")?;
+        writeln!(target, "        <triangles>")?;
+        for [a, b, c] in &faces {
+            writeln!(
+                target,
+                r#"          <triangle v1="{}" v2="{}" v3="{}" />"#,
+                a, b, c
+            )?;
+        }
+        writeln!(target, "        </triangles>")?;
+        writeln!(target, "      </mesh>")?;
+        writeln!(target, "    </object>")?;
+    }
+
+    writeln!(target, "  </resources>")?;
+    writeln!(target, "  <build>")?;
+    for i in 0..parts.len() {
+        writeln!(target, r#"    <item objectid="{}" />"#, i + 2)?;
+    }
+    writeln!(target, "  </build>")?;
+    writeln!(target, "</model>")?;
+
+    Ok(Nothing)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
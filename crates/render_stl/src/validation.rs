@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+
+use project::Failure;
+use rpt::{glm::DVec3, Triangle};
+use serde::Serialize;
+
+use crate::{
+    analysis::{to_position, vertex_key},
+    geometry::{area_vector, bounds},
+    location::Position,
+};
+
+const DEGENERATE_AREA: f64 = 1e-12;
+const INTERSECTION_EPSILON: f64 = 1e-9;
+
+/// Something about a mesh that will likely make a slicer reject it or
+/// produce a broken print. Triangles are identified by their index in the
+/// mesh.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Issue {
+    /// An edge used by only one triangle, so the mesh has a hole.
+    OpenEdge {
+        from: Position,
+        to: Position,
+        triangle: usize,
+    },
+    /// An edge shared by more than two triangles.
+    NonManifoldEdge {
+        from: Position,
+        to: Position,
+        triangles: Vec<usize>,
+    },
+    /// Two neighbours that traverse their shared edge in the same direction,
+    /// which means one of them is facing the wrong way.
+    InconsistentWinding {
+        from: Position,
+        to: Position,
+        triangles: (usize, usize),
+    },
+    /// A triangle with (near) zero area.
+    DegenerateTriangle { triangle: usize, location: Position },
+    /// Two triangles that don't share a vertex but pass through each other.
+    SelfIntersection {
+        triangles: (usize, usize),
+        location: Position,
+    },
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ValidationReport {
+    pub issues: Vec<Issue>,
+}
+
+impl ValidationReport {
+    pub fn of(triangles: &[Triangle]) -> ValidationReport {
+        let mut issues = vec![];
+
+        let degenerate: Vec<bool> = triangles.iter().map(is_degenerate).collect();
+
+        for (index, triangle) in triangles.iter().enumerate() {
+            if degenerate[index] {
+                issues.push(Issue::DegenerateTriangle {
+                    triangle: index,
+                    location: to_position(&centroid(triangle)),
+                });
+            }
+        }
+
+        edge_issues(triangles, &degenerate, &mut issues);
+        intersection_issues(triangles, &degenerate, &mut issues);
+
+        ValidationReport { issues }
+    }
+
+    /// A mesh is watertight when every edge is shared by exactly two
+    /// triangles.
+    pub fn is_watertight(&self) -> bool {
+        !self.issues.iter().any(|issue| {
+            matches!(
+                issue,
+                Issue::OpenEdge { .. } | Issue::NonManifoldEdge { .. }
+            )
+        })
+    }
+
+    pub fn is_printable(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    pub fn to_json(&self) -> Result<String, Failure> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+struct EdgeUse {
+    triangle: usize,
+    from: DVec3,
+    to: DVec3,
+    forward: bool,
+}
+
+fn edge_issues(triangles: &[Triangle], degenerate: &[bool], issues: &mut Vec<Issue>) {
+    let mut edges: HashMap<_, Vec<EdgeUse>> = HashMap::new();
+
+    for (index, triangle) in triangles.iter().enumerate() {
+        if degenerate[index] {
+            continue;
+        }
+
+        let Triangle { v1, v2, v3, .. } = *triangle;
+
+        for (from, to) in [(v1, v2), (v2, v3), (v3, v1)] {
+            let (from_key, to_key) = (vertex_key(&from), vertex_key(&to));
+            let forward = from_key < to_key;
+            let key = if forward {
+                (from_key, to_key)
+            } else {
+                (to_key, from_key)
+            };
+
+            edges.entry(key).or_default().push(EdgeUse {
+                triangle: index,
+                from,
+                to,
+                forward,
+            });
+        }
+    }
+
+    // Several edges can first appear in the same triangle, so the edge
+    // itself breaks ties to keep the order of issues stable.
+    let mut edges: Vec<_> = edges.into_iter().collect();
+    edges.sort_by_key(|(key, uses)| (uses[0].triangle, *key));
+
+    for (_, uses) in edges {
+        let (from, to) = (to_position(&uses[0].from), to_position(&uses[0].to));
+
+        match uses.as_slice() {
+            [only] => issues.push(Issue::OpenEdge {
+                from,
+                to,
+                triangle: only.triangle,
+            }),
+            [first, second] => {
+                if first.forward == second.forward {
+                    issues.push(Issue::InconsistentWinding {
+                        from,
+                        to,
+                        triangles: (first.triangle, second.triangle),
+                    })
+                }
+            }
+            _ => issues.push(Issue::NonManifoldEdge {
+                from,
+                to,
+                triangles: uses.iter().map(|edge| edge.triangle).collect(),
+            }),
+        }
+    }
+}
+
+// Sweep along the x axis so that only triangles with overlapping bounding
+// boxes are compared. Coplanar overlaps aren't detected.
+fn intersection_issues(triangles: &[Triangle], degenerate: &[bool], issues: &mut Vec<Issue>) {
+    let boxes: Vec<_> = triangles
+        .iter()
+        .map(|triangle| bounds(std::slice::from_ref(triangle)))
+        .collect();
+
+    let mut order: Vec<usize> = (0..triangles.len())
+        .filter(|index| !degenerate[*index])
+        .collect();
+    order.sort_by(|a, b| boxes[*a].p_min.x.total_cmp(&boxes[*b].p_min.x));
+
+    for (position, &a) in order.iter().enumerate() {
+        for &b in &order[position + 1..] {
+            if boxes[b].p_min.x > boxes[a].p_max.x {
+                break;
+            }
+
+            if boxes[b].p_min.y > boxes[a].p_max.y
+                || boxes[b].p_max.y < boxes[a].p_min.y
+                || boxes[b].p_min.z > boxes[a].p_max.z
+                || boxes[b].p_max.z < boxes[a].p_min.z
+                || shares_vertex(&triangles[a], &triangles[b])
+            {
+                continue;
+            }
+
+            if let Some(location) = intersect(&triangles[a], &triangles[b]) {
+                issues.push(Issue::SelfIntersection {
+                    triangles: (a.min(b), a.max(b)),
+                    location: to_position(&location),
+                });
+            }
+        }
+    }
+}
+
+fn intersect(a: &Triangle, b: &Triangle) -> Option<DVec3> {
+    edges(a)
+        .iter()
+        .find_map(|(from, to)| segment_intersection(from, to, b))
+        .or_else(|| {
+            edges(b)
+                .iter()
+                .find_map(|(from, to)| segment_intersection(from, to, a))
+        })
+}
+
+// Möller–Trumbore, restricted to the segment between `from` and `to`.
+fn segment_intersection(from: &DVec3, to: &DVec3, triangle: &Triangle) -> Option<DVec3> {
+    let direction = to - from;
+    let edge1 = triangle.v2 - triangle.v1;
+    let edge2 = triangle.v3 - triangle.v1;
+    let p = direction.cross(&edge2);
+    let determinant = edge1.dot(&p);
+
+    if determinant.abs() < INTERSECTION_EPSILON {
+        return None;
+    }
+
+    let inverse = 1.0 / determinant;
+    let s = from - triangle.v1;
+    let u = s.dot(&p) * inverse;
+
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let q = s.cross(&edge1);
+    let v = direction.dot(&q) * inverse;
+
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let t = edge2.dot(&q) * inverse;
+
+    if (0.0..=1.0).contains(&t) {
+        Some(from + direction * t)
+    } else {
+        None
+    }
+}
+
+fn edges(triangle: &Triangle) -> [(DVec3, DVec3); 3] {
+    [
+        (triangle.v1, triangle.v2),
+        (triangle.v2, triangle.v3),
+        (triangle.v3, triangle.v1),
+    ]
+}
+
+fn shares_vertex(a: &Triangle, b: &Triangle) -> bool {
+    let b = [vertex_key(&b.v1), vertex_key(&b.v2), vertex_key(&b.v3)];
+
+    [a.v1, a.v2, a.v3]
+        .iter()
+        .any(|vertex| b.contains(&vertex_key(vertex)))
+}
+
+pub(crate) fn is_degenerate(triangle: &Triangle) -> bool {
+    area_vector(triangle).norm() < DEGENERATE_AREA
+}
+
+pub(crate) fn centroid(triangle: &Triangle) -> DVec3 {
+    (triangle.v1 + triangle.v2 + triangle.v3) / 3.0
+}
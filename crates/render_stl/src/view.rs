@@ -0,0 +1,124 @@
+use std::{fmt, str::FromStr};
+
+use rpt::glm::DVec3;
+
+use crate::{analysis::MeshAnalysis, camera::Camera, model::RenderSettings};
+
+// Leave a little room around the mesh's bounding sphere.
+const MARGIN: f64 = 1.05;
+
+/// A preset camera angle. Meshes are treated as Z-up, the way they sit on a
+/// print bed, so `Front` looks along +Y.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum View {
+    Front,
+    Back,
+    Left,
+    Right,
+    Top,
+    Bottom,
+    /// From above the front right corner.
+    Isometric,
+}
+
+impl View {
+    pub const ALL: [View; 7] = [
+        View::Front,
+        View::Back,
+        View::Left,
+        View::Right,
+        View::Top,
+        View::Bottom,
+        View::Isometric,
+    ];
+
+    /// A camera looking at the centre of `analysis`'s bounds from this
+    /// direction, far enough back that the whole mesh fits in the image.
+    pub fn camera(self, analysis: &MeshAnalysis, settings: &RenderSettings) -> Camera {
+        let (direction, up) = self.axes();
+
+        frame(direction, up, analysis, settings)
+    }
+
+    fn axes(self) -> (DVec3, DVec3) {
+        let z = DVec3::new(0.0, 0.0, 1.0);
+
+        match self {
+            View::Front => (DVec3::new(0.0, 1.0, 0.0), z),
+            View::Back => (DVec3::new(0.0, -1.0, 0.0), z),
+            View::Left => (DVec3::new(1.0, 0.0, 0.0), z),
+            View::Right => (DVec3::new(-1.0, 0.0, 0.0), z),
+            View::Top => (DVec3::new(0.0, 0.0, -1.0), DVec3::new(0.0, 1.0, 0.0)),
+            View::Bottom => (DVec3::new(0.0, 0.0, 1.0), DVec3::new(0.0, 1.0, 0.0)),
+            View::Isometric => (DVec3::new(-1.0, 1.0, -1.0), z),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            View::Front => "front",
+            View::Back => "back",
+            View::Left => "left",
+            View::Right => "right",
+            View::Top => "top",
+            View::Bottom => "bottom",
+            View::Isometric => "iso",
+        }
+    }
+}
+
+// `View::camera` for any direction.
+pub(crate) fn frame(
+    direction: DVec3,
+    up: DVec3,
+    analysis: &MeshAnalysis,
+    settings: &RenderSettings,
+) -> Camera {
+    let camera = Camera::default();
+
+    let min = DVec3::new(analysis.min.0, analysis.min.1, analysis.min.2);
+    let max = DVec3::new(analysis.max.0, analysis.max.1, analysis.max.2);
+    let center = (min + max) / 2.0;
+    let radius = ((max - min).norm() / 2.0).max(f64::EPSILON);
+
+    // The field of view spans the longer side of the image.
+    let (long, short) = (
+        settings.width.max(settings.height).max(1) as f64,
+        settings.width.min(settings.height).max(1) as f64,
+    );
+    let fov: f64 = camera.field_of_view().into();
+    let half_fov = ((fov / 2.0).tan() * short / long).atan();
+    let distance = radius / half_fov.sin() * MARGIN;
+
+    let eye = center - direction.normalize() * distance;
+
+    camera
+        .eye((eye.x, eye.y, eye.z))
+        .direction((direction.x, direction.y, direction.z))
+        .up((up.x, up.y, up.z))
+}
+
+impl fmt::Display for View {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for View {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<View, String> {
+        View::ALL
+            .iter()
+            .copied()
+            .find(|view| view.name() == name || (name == "isometric" && *view == View::Isometric))
+            .ok_or_else(|| {
+                let names: Vec<_> = View::ALL.iter().map(|view| view.name()).collect();
+                format!(
+                    "Unknown view {:?}, expected one of {}",
+                    name,
+                    names.join(", ")
+                )
+            })
+    }
+}
@@ -0,0 +1,49 @@
+// Not every test uses every helper.
+#![allow(dead_code)]
+
+use rpt::{glm::DVec3, Triangle};
+
+// Corners of each face, counter-clockwise seen from outside. Corner `i` has
+// bit 0 set for +X, bit 1 for +Y and bit 2 for +Z.
+const FACES: [[usize; 4]; 6] = [
+    [0, 2, 3, 1],
+    [4, 5, 7, 6],
+    [0, 1, 5, 4],
+    [2, 6, 7, 3],
+    [0, 4, 6, 2],
+    [1, 3, 7, 5],
+];
+
+/// Two outward-facing triangles per face, face by face: bottom, top, front,
+/// back, left and right.
+pub fn cube(min: (f64, f64, f64), size: f64) -> Vec<Triangle> {
+    let corner = |i: usize| {
+        DVec3::new(
+            min.0 + (i & 1) as f64 * size,
+            min.1 + (i >> 1 & 1) as f64 * size,
+            min.2 + (i >> 2 & 1) as f64 * size,
+        )
+    };
+
+    FACES
+        .iter()
+        .flat_map(|[a, b, c, d]| {
+            [
+                Triangle::from_vertices(corner(*a), corner(*b), corner(*c)),
+                Triangle::from_vertices(corner(*a), corner(*c), corner(*d)),
+            ]
+        })
+        .collect()
+}
+
+pub fn flipped(triangle: &Triangle) -> Triangle {
+    Triangle::from_vertices(triangle.v1, triangle.v3, triangle.v2)
+}
+
+/// Positive for a closed mesh that faces outwards.
+pub fn signed_volume(triangles: &[Triangle]) -> f64 {
+    triangles
+        .iter()
+        .map(|triangle| triangle.v1.dot(&triangle.v2.cross(&triangle.v3)) / 6.0)
+        .sum()
+}
@@ -0,0 +1,143 @@
+mod common;
+
+use render_stl::repair::repair;
+use rpt::{glm::DVec3, Triangle};
+
+use common::{cube, flipped, signed_volume};
+
+fn assert_closed_unit_cube(triangles: &[Triangle]) {
+    let volume = signed_volume(triangles);
+    assert!((volume - 1.0).abs() < 1e-9, "volume {}", volume);
+
+    // Every edge is shared by two faces that run along it in opposite
+    // directions.
+    for triangle in triangles {
+        for (from, to) in [
+            (triangle.v1, triangle.v2),
+            (triangle.v2, triangle.v3),
+            (triangle.v3, triangle.v1),
+        ] {
+            let reversed = triangles
+                .iter()
+                .filter(|other| {
+                    [
+                        (other.v1, other.v2),
+                        (other.v2, other.v3),
+                        (other.v3, other.v1),
+                    ]
+                    .contains(&(to, from))
+                })
+                .count();
+            assert_eq!(reversed, 1, "edge {:?} to {:?}", from, to);
+        }
+    }
+}
+
+#[test]
+fn clean_cube() {
+    let (triangles, report) = repair(&cube((0.0, 0.0, 0.0), 1.0), 1e-6).unwrap();
+
+    assert_eq!(triangles.len(), 12);
+    assert_closed_unit_cube(&triangles);
+    assert!(report.is_unchanged(), "{:?}", report);
+}
+
+#[test]
+fn flipped_face() {
+    let mut triangles = cube((0.0, 0.0, 0.0), 1.0);
+    triangles[5] = flipped(&triangles[5]);
+
+    let (triangles, report) = repair(&triangles, 1e-6).unwrap();
+
+    assert_eq!(triangles.len(), 12);
+    assert_closed_unit_cube(&triangles);
+    assert_eq!(report.flipped_faces, 1);
+    assert_eq!(report.filled_holes, 0);
+    assert_eq!(report.added_triangles, 0);
+}
+
+#[test]
+fn missing_face() {
+    // Without the top, the hole is a square, filled with a fan of four
+    // triangles around its centre.
+    let triangles: Vec<_> = cube((0.0, 0.0, 0.0), 1.0)
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| !(2..4).contains(index))
+        .map(|(_, triangle)| triangle)
+        .collect();
+
+    let (triangles, report) = repair(&triangles, 1e-6).unwrap();
+
+    assert_eq!(triangles.len(), 14);
+    assert_closed_unit_cube(&triangles);
+    assert_eq!(report.filled_holes, 1);
+    assert_eq!(report.added_triangles, 4);
+    assert_eq!(report.flipped_faces, 0);
+}
+
+#[test]
+fn inside_out() {
+    let triangles: Vec<_> = cube((0.0, 0.0, 0.0), 1.0).iter().map(flipped).collect();
+
+    let (triangles, report) = repair(&triangles, 1e-6).unwrap();
+
+    assert_closed_unit_cube(&triangles);
+    assert_eq!(report.flipped_faces, 12);
+    assert_eq!(report.welded_vertices, 0);
+}
+
+#[test]
+fn welds_nearby_vertices() {
+    let mut triangles = cube((0.0, 0.0, 0.0), 1.0);
+    // Only the first copy of each corner is kept, so move the later ones.
+    let nudge = DVec3::new(1e-7, -1e-7, 0.0);
+    for triangle in &mut triangles[6..] {
+        triangle.v1 += nudge;
+        triangle.v2 += nudge;
+        triangle.v3 += nudge;
+    }
+
+    let (triangles, report) = repair(&triangles, 1e-6).unwrap();
+
+    assert_eq!(triangles.len(), 12);
+    assert_closed_unit_cube(&triangles);
+    // Each of the eight corners has a nudged copy in the last three faces.
+    assert_eq!(report.welded_vertices, 8);
+    assert_eq!(report.removed_degenerate, 0);
+}
+
+#[test]
+fn zero_tolerance() {
+    let (triangles, report) = repair(&cube((5000.0, -5000.0, 1e12), 1.0), 0.0).unwrap();
+
+    assert_eq!(triangles.len(), 12);
+    assert!(signed_volume(&triangles) > 0.0);
+    assert!(report.is_unchanged(), "{:?}", report);
+
+    let mut nudged = cube((0.0, 0.0, 0.0), 1.0);
+    nudged[0].v1 += DVec3::new(1e-9, 0.0, 0.0);
+    let (_, report) = repair(&nudged, 0.0).unwrap();
+    assert_eq!(report.welded_vertices, 0);
+}
+
+#[test]
+fn degenerate() {
+    let mut triangles = cube((0.0, 0.0, 0.0), 1.0);
+    let corner = triangles[0].v1;
+    triangles.push(Triangle::from_vertices(corner, corner, triangles[0].v2));
+
+    let (triangles, report) = repair(&triangles, 1e-6).unwrap();
+
+    assert_eq!(triangles.len(), 12);
+    assert_closed_unit_cube(&triangles);
+    assert_eq!(report.removed_degenerate, 1);
+}
+
+#[test]
+fn bad_tolerance() {
+    let triangles = cube((0.0, 0.0, 0.0), 1.0);
+
+    assert!(repair(&triangles, -1.0).is_err());
+    assert!(repair(&triangles, f64::NAN).is_err());
+}
@@ -0,0 +1,136 @@
+use render_stl::{
+    direction::Direction, Angle, Camera, Color, LightSource, Location, Material, Rotation,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+
+fn round_trip<T: Serialize + DeserializeOwned>(value: &T, expected: Value) {
+    let written = serde_json::to_value(value).unwrap();
+    assert_eq!(written, expected);
+
+    let read: T = serde_json::from_value(written.clone()).unwrap();
+    assert_eq!(serde_json::to_value(&read).unwrap(), written);
+}
+
+#[test]
+fn angle() {
+    round_trip(&Angle::degrees(90.0), json!("90deg"));
+
+    let angle: Angle = serde_json::from_value(json!("3.141592653589793rad")).unwrap();
+    assert!((angle.to_degrees() - 180.0).abs() < 1e-9);
+
+    let angle: Angle = serde_json::from_value(json!(30)).unwrap();
+    assert!((angle.to_degrees() - 30.0).abs() < 1e-9);
+
+    assert!(serde_json::from_value::<Angle>(json!("30 degrees")).is_err());
+}
+
+#[test]
+fn color() {
+    round_trip(&Color::hex(0xff8000), json!("#ff8000"));
+
+    let color: Color = serde_json::from_value(json!(0x00ff00)).unwrap();
+    assert_eq!(color.rgb(), (0, 255, 0));
+
+    assert!(serde_json::from_value::<Color>(json!("#ff80")).is_err());
+}
+
+#[test]
+fn location() {
+    round_trip(&Location::new(1.0, -2.5, 3.0), json!([1.0, -2.5, 3.0]));
+}
+
+#[test]
+fn direction() {
+    let direction: Direction = (0.0, 0.0, 2.0).into();
+    round_trip(&direction, json!([0.0, 0.0, 1.0]));
+
+    let direction: Direction = serde_json::from_value(json!([3.0, 0.0, 0.0])).unwrap();
+    assert_eq!(
+        serde_json::to_value(direction).unwrap(),
+        json!([1.0, 0.0, 0.0])
+    );
+
+    let error = serde_json::from_value::<Direction>(json!([0, 0, 0])).unwrap_err();
+    assert!(
+        error.to_string().contains("direction can't be zero"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn rotation() {
+    round_trip(
+        &Rotation::euler(Angle::degrees(90.0), Angle::ZERO, Angle::degrees(180.0)),
+        json!({ "x": "90deg", "y": "0deg", "z": "180deg", "convention": "euler" }),
+    );
+    round_trip(
+        &Rotation::ZERO.y(Angle::degrees(45.0)),
+        json!({ "x": "0deg", "y": "45deg", "z": "0deg", "convention": "axis" }),
+    );
+
+    let rotation: Rotation = serde_json::from_value(json!({ "y": "45deg" })).unwrap();
+    assert!(!rotation.is_euler());
+}
+
+#[test]
+fn material() {
+    round_trip(
+        &Material::specular(Color::hex(0x336699), 0.25).metallic(1.0),
+        json!({
+            "color": "#336699",
+            "refraction": 1.5,
+            "roughness": 0.25,
+            "metallic": 1.0,
+            "emittance": 0.0,
+            "transparent": false,
+        }),
+    );
+}
+
+#[test]
+fn light_source() {
+    round_trip(
+        &LightSource::ambient(Color::hex(0x202020)),
+        json!({ "kind": "ambient", "color": "#202020" }),
+    );
+    round_trip(
+        &LightSource::directional(Color::WHITE, (0, -1, 0)),
+        json!({ "kind": "directional", "color": "#ffffff", "direction": [0.0, -1.0, 0.0] }),
+    );
+    round_trip(
+        &LightSource::point(Color::WHITE, Location::new(0.0, 5.0, 5.0)),
+        json!({ "kind": "point", "color": "#ffffff", "location": [0.0, 5.0, 5.0] }),
+    );
+
+    assert!(serde_json::from_value::<LightSource>(json!({ "kind": "spot" })).is_err());
+}
+
+#[test]
+fn camera() {
+    round_trip(
+        &Camera::default()
+            .eye((0.0, 5.0, 10.0))
+            .fov(Angle::degrees(30.0)),
+        json!({
+            "eye": [0.0, 5.0, 10.0],
+            "direction": [0.0, 0.0, -1.0],
+            "up": [0.0, 1.0, 0.0],
+            "fov": "30deg",
+            "aperture": 0.0,
+            "focal_distance": 0.0,
+        }),
+    );
+}
+
+#[test]
+fn toml() {
+    let light: LightSource =
+        toml::from_str("kind = \"point\"\ncolor = \"#ff0000\"\nlocation = [1, 2, 3]\n").unwrap();
+
+    assert_eq!(
+        toml::to_string(&light).unwrap(),
+        "kind = \"point\"\ncolor = \"#ff0000\"\nlocation = [1.0, 2.0, 3.0]\n"
+    );
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use render_stl::{SlicePreview, Slices};
+use rpt::{glm::DVec3, Triangle};
+
+use common::{cube, flipped};
+
+#[test]
+fn cube_layers() {
+    let slices = Slices::of(&cube((0.0, 0.0, 0.0), 10.0), 1.0).unwrap();
+
+    assert_eq!(slices.layers.len(), 10);
+    for (index, layer) in slices.layers.iter().enumerate() {
+        assert!(
+            (layer.z - (index as f64 + 0.5)).abs() < 1e-9,
+            "z {}",
+            layer.z
+        );
+        assert_eq!(layer.open_chains, 0);
+        assert_eq!(layer.polygons.len(), 1);
+        assert!(!layer.polygons[0].is_hole());
+        assert!((layer.area() - 100.0).abs() < 1e-9, "area {}", layer.area());
+    }
+}
+
+#[test]
+fn partial_top_layer() {
+    let slices = Slices::of(&cube((0.0, 0.0, 0.0), 1.0), 0.3).unwrap();
+
+    assert_eq!(slices.layers.len(), 4);
+    assert!(slices.layers.iter().all(|layer| layer.z <= 1.0));
+}
+
+#[test]
+fn hollow_box() {
+    // The inner cube faces inwards, so it cuts out a hole in each layer.
+    let mut triangles = cube((0.0, 0.0, 0.0), 10.0);
+    triangles.extend(cube((3.0, 3.0, 3.0), 4.0).iter().map(flipped));
+
+    let slices = Slices::of(&triangles, 1.0).unwrap();
+
+    for layer in &slices.layers {
+        let holes = layer.polygons.iter().filter(|p| p.is_hole()).count();
+        let expected = if layer.z > 3.0 && layer.z < 7.0 {
+            (1, 84.0)
+        } else {
+            (0, 100.0)
+        };
+
+        assert_eq!(layer.open_chains, 0);
+        assert_eq!(holes, expected.0, "holes at z {}", layer.z);
+        assert!(
+            (layer.area() - expected.1).abs() < 1e-9,
+            "area {} at z {}",
+            layer.area(),
+            layer.z
+        );
+    }
+}
+
+#[test]
+fn bad_layer_height() {
+    let triangles = cube((0.0, 0.0, 0.0), 10.0);
+
+    for layer_height in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+        assert!(
+            Slices::of(&triangles, layer_height).is_err(),
+            "{}",
+            layer_height
+        );
+    }
+}
+
+#[test]
+fn too_many_layers() {
+    let error = Slices::of(&cube((0.0, 0.0, 0.0), 10.0), 1e-9)
+        .err()
+        .unwrap()
+        .to_string();
+
+    assert!(error.contains("more than"), "{}", error);
+}
+
+#[test]
+fn flat_svg() {
+    // A wall with no depth along Y.
+    let wall = [
+        Triangle::from_vertices(
+            DVec3::new(0.0, 0.0, 0.0),
+            DVec3::new(5.0, 0.0, 0.0),
+            DVec3::new(5.0, 0.0, 2.0),
+        ),
+        Triangle::from_vertices(
+            DVec3::new(0.0, 0.0, 0.0),
+            DVec3::new(5.0, 0.0, 2.0),
+            DVec3::new(0.0, 0.0, 2.0),
+        ),
+    ];
+    let slices = Slices::of(&wall, 1.0).unwrap();
+
+    let mut svg = Vec::new();
+    slices
+        .write_svg(0, &SlicePreview::DEFAULT, &mut svg)
+        .unwrap();
+    let svg = String::from_utf8(svg).unwrap();
+
+    assert!(svg.contains(r#"viewBox="0 -2.5 5 5""#), "{}", svg);
+}
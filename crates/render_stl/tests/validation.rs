@@ -0,0 +1,138 @@
+mod common;
+
+use render_stl::ValidationReport;
+use rpt::{glm::DVec3, Triangle};
+use serde_json::Value;
+
+use common::{cube, flipped};
+
+fn issues(triangles: &[Triangle]) -> Vec<Value> {
+    let report = ValidationReport::of(triangles);
+    let json: Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+
+    json["issues"].as_array().unwrap().clone()
+}
+
+fn kinds(issues: &[Value]) -> Vec<&str> {
+    issues
+        .iter()
+        .map(|issue| issue["kind"].as_str().unwrap())
+        .collect()
+}
+
+#[test]
+fn closed_cube() {
+    let report = ValidationReport::of(&cube((0.0, 0.0, 0.0), 1.0));
+
+    assert!(report.issues.is_empty(), "{:?}", report.issues);
+    assert!(report.is_watertight());
+    assert!(report.is_printable());
+}
+
+#[test]
+fn hole() {
+    let mut triangles = cube((0.0, 0.0, 0.0), 1.0);
+    triangles.remove(2);
+
+    let issues = issues(&triangles);
+
+    assert_eq!(kinds(&issues), ["open_edge"; 3]);
+    assert!(!ValidationReport::of(&triangles).is_watertight());
+}
+
+#[test]
+fn non_manifold_edge() {
+    let mut triangles = cube((0.0, 0.0, 0.0), 1.0);
+    // A fin hanging off the bottom front edge.
+    triangles.push(Triangle::from_vertices(
+        DVec3::new(0.0, 0.0, 0.0),
+        DVec3::new(1.0, 0.0, 0.0),
+        DVec3::new(0.5, -1.0, -1.0),
+    ));
+
+    let issues = issues(&triangles);
+    let kinds = kinds(&issues);
+
+    assert_eq!(
+        kinds
+            .iter()
+            .filter(|kind| **kind == "non_manifold_edge")
+            .count(),
+        1
+    );
+    assert_eq!(kinds.iter().filter(|kind| **kind == "open_edge").count(), 2);
+
+    let edge = issues
+        .iter()
+        .find(|issue| issue["kind"] == "non_manifold_edge")
+        .unwrap();
+    assert_eq!(edge["triangles"].as_array().unwrap().len(), 3);
+}
+
+#[test]
+fn interpenetrating_boxes() {
+    let mut triangles = cube((0.0, 0.0, 0.0), 1.0);
+    triangles.extend(cube((0.5, 0.5, 0.5), 1.0));
+
+    let issues = issues(&triangles);
+    let kinds = kinds(&issues);
+
+    assert!(!kinds.is_empty());
+    assert!(
+        kinds.iter().all(|kind| *kind == "self_intersection"),
+        "{:?}",
+        kinds
+    );
+
+    // Every intersection is between one triangle from each box.
+    for issue in &issues {
+        let pair = issue["triangles"].as_array().unwrap();
+        let (a, b) = (pair[0].as_u64().unwrap(), pair[1].as_u64().unwrap());
+        assert!((a < 12) != (b < 12), "{} and {}", a, b);
+    }
+}
+
+#[test]
+fn flipped_face() {
+    let mut triangles = cube((0.0, 0.0, 0.0), 1.0);
+    triangles[5] = flipped(&triangles[5]);
+
+    let issues = issues(&triangles);
+
+    assert_eq!(kinds(&issues), ["inconsistent_winding"; 3]);
+    for issue in &issues {
+        assert!(
+            issue["triangles"]
+                .as_array()
+                .unwrap()
+                .contains(&Value::from(5)),
+            "{}",
+            issue
+        );
+    }
+    assert!(ValidationReport::of(&triangles).is_watertight());
+}
+
+#[test]
+fn zero_area_triangle() {
+    let mut triangles = cube((0.0, 0.0, 0.0), 1.0);
+    let corner = DVec3::new(1.0, 1.0, 1.0);
+    triangles.push(Triangle::from_vertices(corner, corner, DVec3::zeros()));
+
+    let issues = issues(&triangles);
+
+    assert_eq!(kinds(&issues), ["degenerate_triangle"]);
+    assert_eq!(issues[0]["triangle"], 12);
+}
+
+#[test]
+fn stable_order() {
+    let mut triangles = cube((0.0, 0.0, 0.0), 1.0);
+    triangles.remove(2);
+    triangles[7] = flipped(&triangles[7]);
+
+    let first = ValidationReport::of(&triangles).to_json().unwrap();
+    for _ in 0..10 {
+        assert_eq!(ValidationReport::of(&triangles).to_json().unwrap(), first);
+    }
+}